@@ -0,0 +1,301 @@
+use std::path::Path;
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    config_overrides::ConfigOverrides,
+    control_api::{send_control_request, ControlRequest},
+    config_validation::{has_errors, validate_config},
+    secrets_utils::{load_secrets, read_key_material, save_secrets, SECRET_REFERENCE_PREFIX},
+    single_instance::HandoffRequest,
+    windows_os_utils::attach_parent_console,
+};
+
+// Options of the app start, a second launch hands them over to the running instance
+pub struct LaunchOptions {
+    pub profile: Option<String>,
+    pub is_fetch_now_requested: bool,
+}
+
+impl LaunchOptions {
+    pub fn parse(args: &[String]) -> Self {
+        let mut launch_options = LaunchOptions {
+            profile: None,
+            is_fetch_now_requested: false,
+        };
+        let mut args_iter = args.iter();
+
+        while let Some(arg) = args_iter.next() {
+            if arg == "--profile" {
+                launch_options.profile = args_iter.next().cloned();
+            } else if let Some(profile) = arg.strip_prefix("--profile=") {
+                launch_options.profile = Some(profile.to_string());
+            } else if arg == "--fetch-now" {
+                launch_options.is_fetch_now_requested = true;
+            }
+        }
+
+        launch_options
+    }
+
+    // Without options a second launch brings the window of the running instance to the front
+    pub fn handoff_requests(&self) -> Vec<HandoffRequest> {
+        let mut handoff_requests = Vec::new();
+
+        if let Some(profile) = &self.profile {
+            handoff_requests.push(HandoffRequest::SwitchProfile(profile.clone()));
+        }
+        if self.is_fetch_now_requested {
+            handoff_requests.push(HandoffRequest::FetchNow);
+        }
+        if handoff_requests.is_empty() {
+            handoff_requests.push(HandoffRequest::ShowWindow);
+        }
+
+        handoff_requests
+    }
+}
+
+// Returns the process exit code when the arguments name a command, None when the GUI should start
+pub fn run_cli_command(args: &[String], config_overrides: &ConfigOverrides) -> Option<i32> {
+    let positional_args = positional_args(args);
+
+    let exit_code = match positional_args
+        .iter()
+        .map(|arg| arg.as_str())
+        .collect::<Vec<&str>>()
+        .as_slice()
+    {
+        [] => return None,
+        ["config", "validate"] => {
+            attach_parent_console();
+            config_validate(config_overrides)
+        }
+        ["secrets", "add", secret_name] => {
+            attach_parent_console();
+            secrets_add(config_overrides, secret_name)
+        }
+        ["secrets", "list"] => {
+            attach_parent_console();
+            secrets_list(config_overrides)
+        }
+        ["secrets", "remove", secret_name] => {
+            attach_parent_console();
+            secrets_remove(config_overrides, secret_name)
+        }
+        ["ctl", ctl_args @ ..] => {
+            attach_parent_console();
+            ctl(ctl_args)
+        }
+        _ => {
+            attach_parent_console();
+            eprintln!("Unknown command: {}", positional_args.join(" "));
+            print_usage();
+            2
+        }
+    };
+
+    Some(exit_code)
+}
+
+// Everything except the --set key=value overrides and the launch options
+fn positional_args(args: &[String]) -> Vec<String> {
+    let mut positional_args = Vec::new();
+    let mut args_iter = args.iter();
+
+    while let Some(arg) = args_iter.next() {
+        if arg == "--set" || arg == "--profile" {
+            args_iter.next();
+        } else if !arg.starts_with("--set=")
+            && !arg.starts_with("--profile=")
+            && arg != "--fetch-now"
+        {
+            positional_args.push(arg.clone());
+        }
+    }
+
+    positional_args
+}
+
+fn print_usage() {
+    println!("Usage:");
+    println!("  pics2wall [--set <key>=<value>]...           start the app");
+    println!("  pics2wall --profile <name>                   start with (or switch the running");
+    println!("                                               app to) the profiles/<name> config");
+    println!("  pics2wall --fetch-now                        start and fetch (or make the running");
+    println!("                                               app fetch) the wallpaper right away");
+    println!("  pics2wall [--set <key>=<value>]... config validate   validate the config file");
+    println!("  pics2wall secrets add <name>                 add or replace an encrypted secret");
+    println!("  pics2wall secrets list                       list the names of stored secrets");
+    println!("  pics2wall secrets remove <name>              remove a stored secret");
+    println!("  pics2wall ctl status                         show the status of the running app");
+    println!("  pics2wall ctl fetch-now                      fetch and set the wallpaper right away");
+    println!("  pics2wall ctl start|stop                     start or stop auto-update");
+    println!("  pics2wall ctl source <kind> <address>        switch the source of the running app");
+    println!("  pics2wall ctl profile <name>                 switch the running app to a profile");
+}
+
+fn config_validate(config_overrides: &ConfigOverrides) -> i32 {
+    let config = match AppConfig::try_read_app_config(config_overrides) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return 1;
+        }
+    };
+
+    let issues = validate_config(&config);
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+
+    if has_errors(&issues) {
+        1
+    } else {
+        println!("Configuration is valid");
+        0
+    }
+}
+
+// Falls back to an interactive prompt when neither the passphrase variable nor a key file is set.
+// A passphrase creating the secrets file is asked for twice, a typo would lock every secret.
+fn secrets_key_material(
+    config_overrides: &ConfigOverrides,
+    is_new_store_possible: bool,
+) -> Result<Vec<u8>, String> {
+    let secrets_key_file = AppConfig::try_read_app_config(config_overrides)
+        .map(|config| config.secrets_key_file.lock().unwrap().clone())
+        .unwrap_or_default();
+
+    read_key_material(&secrets_key_file).or_else(|_| {
+        rpassword::prompt_password("Secrets passphrase: ")
+            .map(String::into_bytes)
+            .map_err(|e| format!("Passphrase could not be read ({})", e))
+            .and_then(|passphrase| {
+                if passphrase.is_empty() {
+                    return Err(String::from("The passphrase must not be empty"));
+                }
+
+                if is_new_store_possible && !Path::new(AppConstants::SECRETS_FILE_LOCATION).exists()
+                {
+                    let repeated_passphrase = rpassword::prompt_password("Repeat the passphrase: ")
+                        .map(String::into_bytes)
+                        .map_err(|e| format!("Passphrase could not be read ({})", e))?;
+
+                    if repeated_passphrase != passphrase {
+                        return Err(String::from(
+                            "The passphrases do not match, the secrets file was not created",
+                        ));
+                    }
+                }
+
+                Ok(passphrase)
+            })
+    })
+}
+
+fn secrets_add(config_overrides: &ConfigOverrides, secret_name: &str) -> i32 {
+    let result = secrets_key_material(config_overrides, true).and_then(|key_material| {
+        let mut secrets = load_secrets(&key_material)?;
+        let secret_value = rpassword::prompt_password(format!("Value of {}: ", secret_name))
+            .map_err(|e| format!("Secret value could not be read ({})", e))?;
+
+        secrets.insert(secret_name.to_string(), secret_value);
+        save_secrets(&secrets, &key_material)
+    });
+
+    match result {
+        Ok(_) => {
+            println!(
+                "Secret {} stored, reference it in the config as \"{}{}\"",
+                secret_name, SECRET_REFERENCE_PREFIX, secret_name
+            );
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn secrets_list(config_overrides: &ConfigOverrides) -> i32 {
+    match secrets_key_material(config_overrides, false)
+        .and_then(|key_material| load_secrets(&key_material))
+    {
+        Ok(secrets) => {
+            for secret_name in secrets.keys() {
+                println!("{}", secret_name);
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+fn secrets_remove(config_overrides: &ConfigOverrides, secret_name: &str) -> i32 {
+    let result = secrets_key_material(config_overrides, false).and_then(|key_material| {
+        let mut secrets = load_secrets(&key_material)?;
+
+        match secrets.remove(secret_name) {
+            Some(_) => save_secrets(&secrets, &key_material),
+            None => Err(format!("Secret {} not found", secret_name)),
+        }
+    });
+
+    match result {
+        Ok(_) => {
+            println!("Secret {} removed", secret_name);
+            0
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+// Client of the control API of the running instance
+fn ctl(ctl_args: &[&str]) -> i32 {
+    let request = match ctl_args {
+        ["status"] => ControlRequest::Status,
+        ["fetch-now"] => ControlRequest::FetchNow,
+        ["start"] => ControlRequest::StartAutoUpdate,
+        ["stop"] => ControlRequest::StopAutoUpdate,
+        ["source", kind, image_address] => ControlRequest::SwitchSource {
+            kind: kind.to_string(),
+            image_address: image_address.to_string(),
+        },
+        ["profile", profile] => ControlRequest::SwitchProfile {
+            profile: profile.to_string(),
+        },
+        _ => {
+            eprintln!("Unknown command: ctl {}", ctl_args.join(" "));
+            print_usage();
+            return 2;
+        }
+    };
+
+    match send_control_request(&request) {
+        Ok(response) if response.ok => {
+            if let Some(status) = response.status {
+                println!("{}", serde_json::to_string_pretty(&status).unwrap());
+            } else {
+                println!("{}", response.message);
+            }
+            0
+        }
+        Ok(response) => {
+            eprintln!("error: {}", response.message);
+            1
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
@@ -1,20 +1,89 @@
 use std::{
     fs::File,
-    io::{Read, Write},
+    io::Read,
+    path::Path,
     sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
 };
 
 use serde::{Deserialize, Serialize};
 
-use crate::{app_constants::AppConstants, app_gui::Enum};
+use crate::{
+    app_constants::AppConstants,
+    app_gui::Enum,
+    command_source::CommandSource,
+    common_utils::write_file_atomically,
+    config_format_utils::{
+        deserialize_config, find_config_file_location, serialize_config, ConfigFormat,
+    },
+    config_overrides::ConfigOverrides,
+    config_validation::{has_errors, validate_config, ValidationSeverity},
+    download_limits::DownloadLimits,
+    duration_utils::shared_duration_serde,
+    feed_source::FeedSource,
+    html_page_source::HtmlPageSource,
+    http_server::HttpServerConfig,
+    image_conversion::ImageConversion,
+    image_validation::ImageRequirements,
+    json_api_source::JsonApiSource,
+    local_folder_source::LocalFolderSource,
+    log_utils::LoggingConfig,
+    metrics::MetricsConfig,
+    mjpeg_source::MjpegSource,
+    network_config::NetworkConfig,
+    source_auth::SourceAuth,
+};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct AppConfig {
     pub image_address: Arc<Mutex<String>>,
-    pub auto_update_interval: Arc<Mutex<u64>>,
+    // One of the kinds in the image source registry, see image_source.rs
+    #[serde(default = "default_source_kind", alias = "source_kind")]
+    pub kind: Arc<Mutex<String>>,
+    #[serde(default)]
+    pub html_page: Arc<Mutex<HtmlPageSource>>,
+    #[serde(default)]
+    pub json_api: Arc<Mutex<JsonApiSource>>,
+    #[serde(default)]
+    pub feed: Arc<Mutex<FeedSource>>,
+    #[serde(default)]
+    pub mjpeg: Arc<Mutex<MjpegSource>>,
+    #[serde(default)]
+    pub local_folder: Arc<Mutex<LocalFolderSource>>,
+    #[serde(default)]
+    pub command: Arc<Mutex<CommandSource>>,
+    #[serde(with = "shared_duration_serde")]
+    pub auto_update_interval: Arc<Mutex<Duration>>,
+    // Cron schedules are not supported, the key is only read to reject it instead of silently
+    // falling back to the interval
+    #[serde(default, alias = "cron", skip_serializing_if = "Option::is_none")]
+    pub auto_update_cron: Option<String>,
     pub is_auto_update_active: Arc<Mutex<bool>>,
     pub wallpaper_fit_style: Arc<Mutex<Enum>>,
     pub file_type: String,
+    #[serde(default)]
+    pub source_auth: Arc<Mutex<SourceAuth>>,
+    #[serde(default)]
+    pub secrets_key_file: Arc<Mutex<String>>,
+    #[serde(default)]
+    pub network: Arc<Mutex<NetworkConfig>>,
+    #[serde(default)]
+    pub download_limits: Arc<Mutex<DownloadLimits>>,
+    #[serde(default)]
+    pub image_requirements: Arc<Mutex<ImageRequirements>>,
+    #[serde(default)]
+    pub image_conversion: Arc<Mutex<ImageConversion>>,
+    #[serde(default)]
+    pub caption_template: Arc<Mutex<String>>,
+    // Local preset catalog files loaded in addition to the built-in one
+    #[serde(default)]
+    pub preset_catalogs: Arc<Mutex<Vec<String>>>,
+    #[serde(default)]
+    pub http_server: Arc<Mutex<HttpServerConfig>>,
+    #[serde(default)]
+    pub metrics: Arc<Mutex<MetricsConfig>>,
+    #[serde(default)]
+    pub logging: Arc<Mutex<LoggingConfig>>,
 }
 
 impl AppConfig {
@@ -23,82 +92,173 @@ impl AppConfig {
             image_address: Arc::new(Mutex::new(String::from(
                 "https://www.mikrob.it/blank_online_test_page.jpg",
             ))),
+            kind: default_source_kind(),
+            html_page: Arc::new(Mutex::new(HtmlPageSource::default())),
+            json_api: Arc::new(Mutex::new(JsonApiSource::default())),
+            feed: Arc::new(Mutex::new(FeedSource::default())),
+            mjpeg: Arc::new(Mutex::new(MjpegSource::default())),
+            local_folder: Arc::new(Mutex::new(LocalFolderSource::default())),
+            command: Arc::new(Mutex::new(CommandSource::default())),
             auto_update_interval: Arc::new(Mutex::new(AppConstants::AUTO_UPDATE_MIN_INTERVAL)),
+            auto_update_cron: None,
             is_auto_update_active: Arc::new(Mutex::new(false)),
             wallpaper_fit_style: Arc::new(Mutex::new(Enum::Center)),
             file_type: String::from(""),
+            source_auth: Arc::new(Mutex::new(SourceAuth::default())),
+            secrets_key_file: Arc::new(Mutex::new(String::from(""))),
+            network: Arc::new(Mutex::new(NetworkConfig::default())),
+            download_limits: Arc::new(Mutex::new(DownloadLimits::default())),
+            image_requirements: Arc::new(Mutex::new(ImageRequirements::default())),
+            image_conversion: Arc::new(Mutex::new(ImageConversion::default())),
+            caption_template: Arc::new(Mutex::new(String::from(""))),
+            preset_catalogs: Arc::new(Mutex::new(Vec::new())),
+            http_server: Arc::new(Mutex::new(HttpServerConfig::default())),
+            metrics: Arc::new(Mutex::new(MetricsConfig::default())),
+            logging: Arc::new(Mutex::new(LoggingConfig::default())),
         }
     }
 
-    pub fn save_app_config(&self) -> String {
-        let serialized_config_json = serde_json::to_string_pretty(&self).unwrap();
-
-        let config_file = File::create(AppConstants::CONFIG_FILE_LOCATION);
-        match config_file {
-            Ok(_) => {
-                let file_write_result = config_file
-                    .unwrap()
-                    .write_all(serialized_config_json.as_bytes());
-                match file_write_result {
-                    Ok(_) => {}
-                    Err(_) => log::error!("Failed writing the config file"),
+    pub fn save_app_config(&self, config_overrides: &ConfigOverrides) -> String {
+        let config_file_location = find_config_file_location();
+        let config_format = ConfigFormat::from_path(&config_file_location);
+        let previous_content = std::fs::read_to_string(&config_file_location).ok();
+
+        let file_config = previous_content
+            .as_deref()
+            .and_then(|content| deserialize_config(content, config_format).ok())
+            .unwrap_or_else(AppConfig::default);
+        let config_to_save = config_overrides.revert(self, &file_config);
+
+        let serialized_config =
+            match serialize_config(&config_to_save, config_format, previous_content.as_deref()) {
+                Ok(serialized_config) => serialized_config,
+                Err(e) => {
+                    log::error!("Failed serializing the config - {}", e);
+                    return String::new();
                 }
-            }
-            Err(_) => log::error!("Failed creating the config file"),
+            };
+
+        match write_file_atomically(Path::new(&config_file_location), serialized_config.as_bytes())
+        {
+            Ok(_) => {}
+            Err(e) => log::error!("Failed writing the config file - {}", e),
         }
 
-        serialized_config_json
+        serialized_config
     }
 
-    pub fn load_app_config() -> Self {
-        let serialized_config_in_file = File::open(AppConstants::CONFIG_FILE_LOCATION);
+    pub fn load_app_config(config_overrides: &ConfigOverrides) -> Self {
+        match AppConfig::try_load_app_config(config_overrides) {
+            Ok(config) => {
+                log::info!("Config deserialized successfully and will be used");
+                config
+            }
+            Err(e) => {
+                log::error!("{}, loading default configuration", e);
 
-        match &serialized_config_in_file {
-            Ok(_) => {}
-            Err(_) => {
-                log::error!("Config file load failed, loading default configuration");
-                return AppConfig::default();
+                match config_overrides
+                    .apply(&AppConfig::default())
+                    .and_then(|config| config.validate().map(|_| config))
+                {
+                    Ok(config) => config,
+                    Err(e) => {
+                        log::error!("Config overrides ignored - {}", e);
+                        AppConfig::default()
+                    }
+                }
             }
         }
+    }
+
+    pub fn try_load_app_config(config_overrides: &ConfigOverrides) -> Result<Self, String> {
+        let config = AppConfig::try_read_app_config(config_overrides)?;
+
+        config
+            .validate()
+            .map_err(|e| format!("Invalid configuration, {}", e))?;
+
+        Ok(config)
+    }
+
+    // Reads the config file and applies the overrides without validating the result
+    pub fn try_read_app_config(config_overrides: &ConfigOverrides) -> Result<Self, String> {
+        let config_file_location = find_config_file_location();
+        let mut serialized_config_in_file = File::open(&config_file_location)
+            .map_err(|_| String::from("Config file load failed"))?;
 
         let mut buffer = String::new();
-        let config_file_read_result = serialized_config_in_file
-            .unwrap()
-            .read_to_string(&mut buffer);
+        serialized_config_in_file
+            .read_to_string(&mut buffer)
+            .map_err(|_| String::from("Config file read failed"))?;
 
-        match config_file_read_result {
-            Ok(_) => {
-                log::info!("Config file read successfully: {}", &buffer)
-            }
-            Err(_) => log::error!("Config file read failed"),
-        }
+        log::info!("Config file {} read successfully", &config_file_location);
 
-        let config_deserialize_result: Result<AppConfig, serde_json::Error> =
-            serde_json::from_str(&buffer);
+        let file_config =
+            deserialize_config(&buffer, ConfigFormat::from_path(&config_file_location))
+                .map_err(|e| format!("Config file deserialize failed ({})", e))?;
 
-        match config_deserialize_result {
-            Ok(_) => {
-                // TODO: Super ugly, rewrite
+        config_overrides.apply(&file_config)
+    }
 
-                if *config_deserialize_result
-                    .as_ref()
-                    .unwrap()
-                    .auto_update_interval
-                    .lock()
-                    .unwrap()
-                    < AppConstants::AUTO_UPDATE_MIN_INTERVAL
-                {
-                    log::error!("Invalid configuration, the auto-update interval is too short. Loading default configuration");
-                    return AppConfig::default();
-                } else {
-                    log::info!("Config deserialized successfully and will be used");
-                    return config_deserialize_result.unwrap();
-                }
-            }
-            Err(_) => {
-                log::error!("Config file deserialize failed, loading default configuration");
-                AppConfig::default()
-            }
+    // Errors reject the configuration, warnings are only logged
+    pub fn validate(&self) -> Result<(), String> {
+        let issues = validate_config(self);
+
+        for issue in issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Warning)
+        {
+            log::warn!("Configuration {}", issue);
+        }
+
+        if has_errors(&issues) {
+            return Err(issues
+                .iter()
+                .filter(|issue| issue.severity == ValidationSeverity::Error)
+                .map(|issue| issue.to_string())
+                .collect::<Vec<String>>()
+                .join("; "));
         }
+
+        Ok(())
+    }
+
+    // Copies values into the existing shared handles so that every clone of the config
+    // (GUI, update thread) sees the change. The auto-update on/off state is runtime
+    // state and is deliberately left untouched.
+    pub fn apply(&self, other: &AppConfig) {
+        *self.image_address.lock().unwrap() = other.image_address.lock().unwrap().clone();
+        *self.kind.lock().unwrap() = other.kind.lock().unwrap().clone();
+        *self.html_page.lock().unwrap() = other.html_page.lock().unwrap().clone();
+        *self.json_api.lock().unwrap() = other.json_api.lock().unwrap().clone();
+        *self.feed.lock().unwrap() = other.feed.lock().unwrap().clone();
+        *self.mjpeg.lock().unwrap() = other.mjpeg.lock().unwrap().clone();
+        *self.local_folder.lock().unwrap() = other.local_folder.lock().unwrap().clone();
+        *self.command.lock().unwrap() = other.command.lock().unwrap().clone();
+        *self.auto_update_interval.lock().unwrap() = *other.auto_update_interval.lock().unwrap();
+        *self.wallpaper_fit_style.lock().unwrap() =
+            other.wallpaper_fit_style.lock().unwrap().clone();
+        *self.source_auth.lock().unwrap() = other.source_auth.lock().unwrap().clone();
+        *self.secrets_key_file.lock().unwrap() = other.secrets_key_file.lock().unwrap().clone();
+        *self.network.lock().unwrap() = other.network.lock().unwrap().clone();
+        *self.download_limits.lock().unwrap() = other.download_limits.lock().unwrap().clone();
+        *self.image_requirements.lock().unwrap() =
+            other.image_requirements.lock().unwrap().clone();
+        *self.image_conversion.lock().unwrap() = other.image_conversion.lock().unwrap().clone();
+        *self.caption_template.lock().unwrap() = other.caption_template.lock().unwrap().clone();
+        *self.preset_catalogs.lock().unwrap() = other.preset_catalogs.lock().unwrap().clone();
+        *self.http_server.lock().unwrap() = other.http_server.lock().unwrap().clone();
+        *self.metrics.lock().unwrap() = other.metrics.lock().unwrap().clone();
+        *self.logging.lock().unwrap() = other.logging.lock().unwrap().clone();
     }
+
+    pub fn config_file_modified_time() -> Option<SystemTime> {
+        std::fs::metadata(find_config_file_location())
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+}
+
+fn default_source_kind() -> Arc<Mutex<String>> {
+    Arc::new(Mutex::new(String::from("Http")))
 }
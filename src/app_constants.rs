@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 #[derive(Clone)]
 pub struct AppConstants {}
 
@@ -10,7 +12,16 @@ impl AppConstants {
 
     // app internal
     pub const TICK_INTERVAL: u64 = 5;
-    pub const AUTO_UPDATE_MIN_INTERVAL: u64 = 60;
+    pub const AUTO_UPDATE_MIN_INTERVAL: Duration = Duration::from_secs(60);
+    pub const FETCH_HISTORY_LENGTH: usize = 50;
+    pub const MAX_REDIRECTIONS: u32 = 10;
+    pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+    // transfers slower than 1 byte per second for this long are aborted as stalled
+    pub const STALLED_TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+    pub const MJPEG_FRAME_TIMEOUT: Duration = Duration::from_secs(60);
+    pub const SUPPORTED_IMAGE_SUFFIXES: [&str; 9] = [
+        ".jpg", ".jpeg", ".png", ".webp", ".gif", ".bmp", ".tif", ".tiff", ".avif",
+    ];
 
     // file locations
     pub const WALLPAPER_IMAGE_FILE_LOCATION: &str = r#"data/"#;
@@ -21,7 +32,19 @@ impl AppConstants {
     pub const IN_PROGRESS_TEST_IMAGE_FILE_LOCATION: &str =
         r#"resources\in_progress_fetch_status_test_page.jpg"#;
     pub const CONFIG_FILE_LOCATION: &str = "config.json";
+    pub const CONFIG_FILE_LOCATION_CANDIDATES: [&str; 4] =
+        ["config.toml", "config.yaml", "config.yml", "config.json"];
     pub const LOG_FILE_LOCATION: &str = "log/";
+    pub const SECRETS_FILE_LOCATION: &str = "secrets.enc";
+    pub const FEED_STATE_FILE_LOCATION: &str = r#"data/feed_state.json"#;
+    pub const PRESET_CATALOG_FILE_LOCATION: &str = r#"resources\presets.json"#;
+    pub const PROFILES_DIRECTORY: &str = "profiles/";
+    pub const RUNTIME_DIRECTORY: &str = "run/";
+    pub const INSTANCE_LOCK_FILE_LOCATION: &str = "run/pics2wall.lock";
+    pub const HANDOFF_FILE_LOCATION: &str = "run/handoff_requests.jsonl";
+
+    // environment
+    pub const SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE: &str = "PICS2WALL_SECRETS_PASSPHRASE";
 
     // TODO: format better
     pub const LICENSE_TEXT: &str =
@@ -1,16 +1,33 @@
 use crate::{
     app_config::AppConfig,
     app_constants::AppConstants,
-    log_utils,
+    config_format_utils::active_profile,
+    config_overrides::ConfigOverrides,
+    config_validation::{
+        has_errors, issues_for_field, validate_config, ValidationIssue, ValidationSeverity,
+    },
+    duration_utils::{format_duration, parse_duration},
+    fetch_result::{FetchHistoryEntry, FetchResult, ImageMetadata, WallpaperValidators},
+    image_source::{find_image_source, image_source_kinds},
+    log_utils::{self, LogLevel},
+    metrics::Metrics,
+    preset_catalog::{load_preset_catalog, load_preset_catalogs, Preset},
+    single_instance::HandoffRequest,
+    source_auth::{mask_url_credentials, AuthMethod},
+    url_template::{
+        expand_caption_template, CAPTION_TEMPLATE_PLACEHOLDERS, URL_TEMPLATE_PLACEHOLDERS,
+    },
     common_utils::{
-        get_current_background_color, is_image_url_valid,
-        load_image_from_path,
+        get_current_background_color,
+        load_resource_image,
     }, windows_os_utils::{set_background_color, clear_background},
 };
-use egui::{Align2, Context, Label, Ui, Vec2};
+use chrono::{DateTime, Local};
+use egui::{Align2, Context, Label, RichText, Ui, Vec2};
 use egui_extras::RetainedImage;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::VecDeque,
     path::Path,
     sync::{Arc, Mutex},
 };
@@ -26,24 +43,41 @@ pub enum Enum {
 pub struct MyApp {
     pub config: AppConfig,
     pub status: Arc<Mutex<String>>,
-    pub last_fetch_result: Arc<Mutex<String>>,
+    pub last_fetch_result: Arc<Mutex<FetchResult>>,
     pub last_fetch_time: Arc<Mutex<String>>,
+    pub last_image_metadata: Arc<Mutex<ImageMetadata>>,
+    pub wallpaper_validators: Arc<Mutex<WallpaperValidators>>,
     pub test_image: Arc<Mutex<RetainedImage>>,
     pub autoupdate_interval_input_string: String,
     pub is_form_valid: bool,
+    pub config_issues: Vec<ValidationIssue>,
     pub is_diagnostic_image_shown: bool,
     pub is_license_info_shown: bool,
     pub is_test_image_fetch_requested: Arc<Mutex<bool>>,
+    pub queued_requests: Arc<Mutex<Vec<HandoffRequest>>>,
+    pub next_update_time: Arc<Mutex<Option<DateTime<Local>>>>,
+    pub fetch_history: Arc<Mutex<VecDeque<FetchHistoryEntry>>>,
+    pub metrics: Arc<Mutex<Metrics>>,
+    pub config_reload_status: Arc<Mutex<String>>,
+    pub config_generation: Arc<Mutex<u64>>,
+    pub seen_config_generation: u64,
+    // the config the current issues were found for, validation reads folders from disk and
+    // does not run on every frame
+    pub validated_config: Option<serde_json::Value>,
+    pub config_overrides: Arc<ConfigOverrides>,
+    pub presets: Vec<Preset>,
+    pub preset_catalog_errors: Vec<String>,
+    pub preset_search_string: String,
+    pub preset_catalog_input_string: String,
+    pub preset_status: String,
 }
 
 impl MyApp {
-    pub fn default() -> Self {
-        let loaded_config = AppConfig::load_app_config();
-        let loaded_auto_update_interval = loaded_config
-            .auto_update_interval
-            .lock()
-            .unwrap()
-            .to_string();
+    pub fn new(config_overrides: ConfigOverrides) -> Self {
+        let loaded_config = AppConfig::load_app_config(&config_overrides);
+        let loaded_auto_update_interval =
+            format_duration(*loaded_config.auto_update_interval.lock().unwrap());
+        let (presets, preset_catalog_errors) = load_preset_catalogs(&loaded_config);
 
         Self {
             // config: AppConfig::new(),
@@ -53,20 +87,36 @@ impl MyApp {
                 let image_path = Path::new(AppConstants::BLANK_TEST_IMAGE_FILE_LOCATION);
                 Arc::new(Mutex::new(RetainedImage::from_color_image(
                     "test_image",
-                    load_image_from_path(image_path).unwrap(),
+                    load_resource_image(image_path).unwrap(),
                 )))
             },
 
-            // TODO: get rid of the intermediate string
-            // Form-validated string for future time unit support
+            // Form-validated string, accepts time units (e.g. 90s, 5m, 1h30m, 2d)
             autoupdate_interval_input_string: loaded_auto_update_interval,
 
-            last_fetch_result: Arc::new(Mutex::new(String::from("N/A"))),
+            last_fetch_result: Arc::new(Mutex::new(FetchResult::NotAttempted)),
             last_fetch_time: Arc::new(Mutex::new(String::from("N/A"))),
+            last_image_metadata: Arc::new(Mutex::new(ImageMetadata::default())),
+            wallpaper_validators: Arc::new(Mutex::new(WallpaperValidators::default())),
             is_form_valid: true,
+            config_issues: Vec::new(),
             is_diagnostic_image_shown: false,
             is_license_info_shown: false,
             is_test_image_fetch_requested: Arc::new(Mutex::new(false)),
+            queued_requests: Arc::new(Mutex::new(Vec::new())),
+            next_update_time: Arc::new(Mutex::new(None)),
+            fetch_history: Arc::new(Mutex::new(VecDeque::new())),
+            metrics: Arc::new(Mutex::new(Metrics::default())),
+            config_reload_status: Arc::new(Mutex::new(String::from("N/A"))),
+            config_generation: Arc::new(Mutex::new(0)),
+            seen_config_generation: 0,
+            validated_config: None,
+            config_overrides: Arc::new(config_overrides),
+            presets,
+            preset_catalog_errors,
+            preset_search_string: String::new(),
+            preset_catalog_input_string: String::new(),
+            preset_status: String::new(),
         }
     }
 }
@@ -84,9 +134,11 @@ impl eframe::App for MyApp {
             //     .show(ctx, |ui| {
             ui_add_status(ui, self);
             ui_add_config(ui, self);
+            ui_add_presets(ui, self);
             ui_add_controls(ui, self);
             ui_add_diagnostic_tools(ui, self, _frame);
             ui_add_test_image(self, ctx);
+            ui_add_image_metadata(self, ctx);
 
             ui_add_dev_version_info(self, ctx);
             ui_add_license_info(self, ctx);
@@ -119,6 +171,47 @@ fn ui_add_status(ui: &mut Ui, my_app: &mut MyApp) {
 
                     ui.label("Last fetch result:");
                     ui.label(my_app.last_fetch_result.lock().unwrap().to_string());
+
+                    ui.end_row();
+
+                    if let Some(next_update_time) = *my_app.next_update_time.lock().unwrap() {
+                        ui.label("Next update:");
+                        ui.label(next_update_time.format("%Y-%m-%d %H:%M:%S").to_string());
+
+                        ui.end_row();
+                    }
+
+                    let image_metadata = my_app.last_image_metadata.lock().unwrap().clone();
+
+                    if !image_metadata.title.is_empty() {
+                        ui.label("Image title:");
+                        ui.label(image_metadata.title);
+
+                        ui.end_row();
+                    }
+
+                    if !image_metadata.credit.is_empty() {
+                        ui.label("Image credit:");
+                        ui.label(image_metadata.credit);
+
+                        ui.end_row();
+                    }
+
+                    if let Some(capture_time) = image_metadata.capture_time {
+                        ui.label("Capture time:");
+                        ui.label(capture_time.format("%Y-%m-%d %H:%M:%S").to_string());
+
+                        ui.end_row();
+                    }
+
+                    let profile = active_profile();
+                    ui.label("Profile:");
+                    ui.label(if profile.is_empty() { "default" } else { profile.as_str() });
+
+                    ui.end_row();
+
+                    ui.label("Config file reload:");
+                    ui.label(my_app.config_reload_status.lock().unwrap().to_string());
                 });
         });
 
@@ -130,6 +223,16 @@ fn ui_add_config(ui: &mut Ui, my_app: &mut MyApp) {
     let desired_right_col_width = 300.0;
     let is_configurable = !*my_app.config.is_auto_update_active.lock().unwrap();
 
+    // pick up values applied by a config file reload
+    let config_generation = *my_app.config_generation.lock().unwrap();
+    if my_app.seen_config_generation != config_generation {
+        my_app.autoupdate_interval_input_string =
+            format_duration(*my_app.config.auto_update_interval.lock().unwrap());
+        my_app.seen_config_generation = config_generation;
+    }
+
+    let mut is_interval_input_changed = false;
+
     egui::CollapsingHeader::new("Configuration")
         .default_open(true)
         .enabled(is_configurable)
@@ -138,51 +241,169 @@ fn ui_add_config(ui: &mut Ui, my_app: &mut MyApp) {
                 .num_columns(2)
                 .min_col_width(min_col_width)
                 .show(ui, |ui| {
-                    ui.label("Image address:");
+                    let is_overridden = ui_add_config_label(ui, my_app, "Source type:", "kind");
+
+                    let kind_handle = &mut *my_app.config.kind.lock().unwrap();
+
+                    ui.add_enabled_ui(!is_overridden, |ui| {
+                        egui::ComboBox::from_id_source("kind")
+                            .selected_text(kind_handle.as_str())
+                            .show_ui(ui, |ui| {
+                                for kind in image_source_kinds() {
+                                    ui.selectable_value(kind_handle, kind.to_string(), kind);
+                                }
+                            });
+                    });
+
+                    ui.end_row();
+
+                    let image_source = find_image_source(kind_handle);
+
+                    let is_overridden = ui_add_config_label(
+                        ui,
+                        my_app,
+                        image_source
+                            .as_ref()
+                            .map_or("Image address:", |image_source| image_source.address_label()),
+                        "image_address",
+                    );
 
                     let image_address_handle = &mut *my_app.config.image_address.lock().unwrap();
-                    let image_address_handle_clone = image_address_handle.clone();
 
                     let source_address_edit_text = egui::TextEdit::singleline(image_address_handle)
                         .desired_width(desired_right_col_width);
 
-                    ui.add(source_address_edit_text);
+                    ui.add_enabled(!is_overridden, source_address_edit_text)
+                        .on_hover_text(format!(
+                            "Placeholders: {}",
+                            URL_TEMPLATE_PLACEHOLDERS.join(", ")
+                        ));
 
                     ui.end_row();
 
-                    ui.label("Update Interval [s]:");
+                    if let Some(image_source) = image_source {
+                        image_source.ui_add_settings(ui, my_app, desired_right_col_width);
+                    }
 
-                    let update_interval_edit_text =
-                        egui::TextEdit::singleline(&mut my_app.autoupdate_interval_input_string)
-                            .desired_width(desired_right_col_width);
+                    let is_overridden = ui_add_config_label(
+                        ui,
+                        my_app,
+                        "Authentication:",
+                        "source_auth.method",
+                    );
+
+                    let source_auth_handle = &mut *my_app.config.source_auth.lock().unwrap();
+
+                    ui.add_enabled_ui(!is_overridden, |ui| {
+                        egui::ComboBox::from_id_source("source_auth_method")
+                            .selected_text(format!("{:?}", source_auth_handle.method))
+                            .show_ui(ui, |ui| {
+                                for auth_method in [
+                                    AuthMethod::None,
+                                    AuthMethod::Basic,
+                                    AuthMethod::Digest,
+                                    AuthMethod::Bearer,
+                                ] {
+                                    ui.selectable_value(
+                                        &mut source_auth_handle.method,
+                                        auth_method,
+                                        format!("{:?}", auth_method),
+                                    );
+                                }
+                            });
+                    });
 
-                    ui.add(update_interval_edit_text);
+                    ui.end_row();
+
+                    match source_auth_handle.method {
+                        AuthMethod::Basic | AuthMethod::Digest => {
+                            let is_overridden = ui_add_config_label(
+                                ui,
+                                my_app,
+                                "Username:",
+                                "source_auth.username",
+                            );
+                            ui.add_enabled(
+                                !is_overridden,
+                                egui::TextEdit::singleline(&mut source_auth_handle.username)
+                                    .desired_width(desired_right_col_width),
+                            );
 
-                    let parsed_input = &mut my_app.autoupdate_interval_input_string.parse::<u64>();
-                    let parsed_input_clone = parsed_input.clone();
+                            ui.end_row();
 
-                    // TODO: Fix ugly
+                            let is_overridden = ui_add_config_label(
+                                ui,
+                                my_app,
+                                "Password:",
+                                "source_auth.password",
+                            );
+                            ui.add_enabled(
+                                !is_overridden,
+                                egui::TextEdit::singleline(&mut source_auth_handle.password)
+                                    .password(true)
+                                    .desired_width(desired_right_col_width),
+                            );
 
-                    match parsed_input {
-                        Ok(_) => {
-                            let mod_parsed_input = parsed_input_clone.unwrap()
-                                % AppConstants::AUTO_UPDATE_MIN_INTERVAL;
-                            let u64_input_string_value = parsed_input.clone().unwrap();
+                            ui.end_row();
+                        }
+                        AuthMethod::Bearer => {
+                            let is_overridden =
+                                ui_add_config_label(ui, my_app, "Token:", "source_auth.token");
+                            ui.add_enabled(
+                                !is_overridden,
+                                egui::TextEdit::singleline(&mut source_auth_handle.token)
+                                    .password(true)
+                                    .desired_width(desired_right_col_width),
+                            );
 
-                            // TODO pattern match
-                            if mod_parsed_input == 0
-                                && u64_input_string_value != 0
-                                && is_image_url_valid(&image_address_handle_clone)
-                            {
-                                *my_app.config.auto_update_interval.lock().unwrap() =
-                                    u64_input_string_value;
-                                my_app.is_form_valid = true;
-                            } else {
-                                my_app.is_form_valid = false;
-                            }
+                            ui.end_row();
                         }
-                        _ => {
-                            my_app.is_form_valid = false;
+                        AuthMethod::None => {}
+                    }
+
+                    if !source_auth_handle.custom_headers.is_empty() {
+                        ui_add_config_label(
+                            ui,
+                            my_app,
+                            "Custom headers:",
+                            "source_auth.custom_headers",
+                        );
+                        ui.label(
+                            source_auth_handle
+                                .masked_headers()
+                                .iter()
+                                .map(|(name, value)| format!("{}: {}", name, value))
+                                .collect::<Vec<String>>()
+                                .join("\n"),
+                        )
+                        .on_hover_text("Custom headers can be edited in the config file");
+
+                        ui.end_row();
+                    }
+
+                    let is_overridden = ui_add_config_label(
+                        ui,
+                        my_app,
+                        "Update Interval:",
+                        "auto_update_interval",
+                    );
+
+                    let update_interval_edit_text =
+                        egui::TextEdit::singleline(&mut my_app.autoupdate_interval_input_string)
+                            .desired_width(desired_right_col_width);
+
+                    let update_interval_response = ui
+                        .add_enabled(!is_overridden, update_interval_edit_text)
+                        .on_hover_text("Seconds or a duration with units, e.g. 90s, 5m, 1h30m, 2d");
+
+                    // only typed values are written, a reloaded interval is not overwritten by
+                    // the text of the input box
+                    if update_interval_response.changed() {
+                        is_interval_input_changed = true;
+                        if let Ok(parsed_input) =
+                            parse_duration(&my_app.autoupdate_interval_input_string)
+                        {
+                            *my_app.config.auto_update_interval.lock().unwrap() = parsed_input;
                         }
                     }
 
@@ -203,30 +424,148 @@ fn ui_add_config(ui: &mut Ui, my_app: &mut MyApp) {
 
                     ui.end_row();
 
-                    ui.label("Fit style:");
+                    let network_handle = &mut *my_app.config.network.lock().unwrap();
+
+                    let is_overridden = ui_add_config_label(ui, my_app, "Proxy:", "network.proxy");
+
+                    // credentials in the proxy URL are only shown while the field is edited
+                    let proxy_edit_id = ui.make_persistent_id("network_proxy");
+                    let mut masked_proxy = mask_url_credentials(&network_handle.proxy);
+                    let proxy_text = if ui.memory().has_focus(proxy_edit_id) {
+                        &mut network_handle.proxy
+                    } else {
+                        &mut masked_proxy
+                    };
+
+                    ui.add_enabled(
+                        !is_overridden,
+                        egui::TextEdit::singleline(proxy_text)
+                            .id(proxy_edit_id)
+                            .hint_text(if network_handle.use_environment_proxy {
+                                "from environment (HTTP_PROXY / HTTPS_PROXY)"
+                            } else {
+                                "none"
+                            })
+                            .desired_width(desired_right_col_width),
+                    )
+                    .on_hover_text("e.g. http://proxy:3128 or socks5://proxy:1080, credentials, certificates and IP version are set in the config file");
+
+                    ui.end_row();
+
+                    let is_overridden =
+                        ui_add_config_label(ui, my_app, "User agent:", "network.user_agent");
+                    ui.add_enabled(
+                        !is_overridden,
+                        egui::TextEdit::singleline(&mut network_handle.user_agent)
+                            .hint_text("libcurl default")
+                            .desired_width(desired_right_col_width),
+                    );
+
+                    ui.end_row();
+
+                    let is_overridden =
+                        ui_add_config_label(ui, my_app, "Fit style:", "wallpaper_fit_style");
 
                     let current_wallpaper_fit_value =
                         &mut *my_app.config.wallpaper_fit_style.lock().unwrap();
 
-                    egui::ComboBox::from_id_source("Take your pick")
-                        .selected_text(format!("{:?}", current_wallpaper_fit_value))
-                        .show_ui(ui, |ui| {
-                            ui.selectable_value(
-                                current_wallpaper_fit_value,
-                                Enum::Center,
-                                "Center",
-                            );
-                            ui.selectable_value(current_wallpaper_fit_value, Enum::Fit, "Fit");
-                            ui.selectable_value(
-                                current_wallpaper_fit_value,
-                                Enum::Stretch,
-                                "Stretch",
-                            );
-                        });
+                    ui.add_enabled_ui(!is_overridden, |ui| {
+                        egui::ComboBox::from_id_source("Take your pick")
+                            .selected_text(format!("{:?}", current_wallpaper_fit_value))
+                            .show_ui(ui, |ui| {
+                                ui.selectable_value(
+                                    current_wallpaper_fit_value,
+                                    Enum::Center,
+                                    "Center",
+                                );
+                                ui.selectable_value(current_wallpaper_fit_value, Enum::Fit, "Fit");
+                                ui.selectable_value(
+                                    current_wallpaper_fit_value,
+                                    Enum::Stretch,
+                                    "Stretch",
+                                );
+                            });
+                    });
+
+                    ui.end_row();
+
+                    let is_overridden =
+                        ui_add_config_label(ui, my_app, "Caption template:", "caption_template");
+                    ui.add_enabled(
+                        !is_overridden,
+                        egui::TextEdit::singleline(
+                            &mut *my_app.config.caption_template.lock().unwrap(),
+                        )
+                        .hint_text("e.g. {title} ({credit}), {capture_time}")
+                        .desired_width(desired_right_col_width),
+                    )
+                    .on_hover_text(format!(
+                        "Placeholders: {}",
+                        CAPTION_TEMPLATE_PLACEHOLDERS.join(", ")
+                    ));
+
+                    ui.end_row();
+
+                    let is_overridden =
+                        ui_add_config_label(ui, my_app, "GIF frame:", "image_conversion.gif_frame");
+                    ui.add_enabled(
+                        !is_overridden,
+                        egui::DragValue::new(
+                            &mut my_app.config.image_conversion.lock().unwrap().gif_frame,
+                        )
+                        .clamp_range(0..=1000),
+                    )
+                    .on_hover_text("Frame of animated GIFs used as wallpaper, 0 is the first one");
+
+                    ui.end_row();
+
+                    let is_overridden =
+                        ui_add_config_label(ui, my_app, "Log level:", "logging.level");
+
+                    let current_log_level = &mut my_app.config.logging.lock().unwrap().level;
+
+                    ui.add_enabled_ui(!is_overridden, |ui| {
+                        egui::ComboBox::from_id_source("Log level")
+                            .selected_text(format!("{:?}", current_log_level))
+                            .show_ui(ui, |ui| {
+                                for log_level in LogLevel::ALL {
+                                    ui.selectable_value(
+                                        current_log_level,
+                                        log_level,
+                                        format!("{:?}", log_level),
+                                    );
+                                }
+                            });
+                    })
+                    .response
+                    .on_hover_text(
+                        "Module levels, rotation and stderr output are set in the config file",
+                    );
 
                     ui.end_row();
                 });
 
+            // an edited input, a reload, a reset or a preset all change the config
+            let form_config = serde_json::to_value(&my_app.config).ok();
+            if is_interval_input_changed || form_config != my_app.validated_config {
+                // switches the level right away instead of on the next update tick
+                log_utils::apply_logging_config(&my_app.config.logging.lock().unwrap());
+
+                my_app.config_issues = validate_config(&my_app.config);
+                if let Err(e) = parse_duration(&my_app.autoupdate_interval_input_string) {
+                    my_app
+                        .config_issues
+                        .retain(|issue| issue.field != "auto_update_interval");
+                    my_app.config_issues.push(ValidationIssue {
+                        field: "auto_update_interval",
+                        severity: ValidationSeverity::Error,
+                        message: format!("Invalid auto-update interval - {}", e),
+                    });
+                }
+                my_app.is_form_valid = !has_errors(&my_app.config_issues);
+                my_app.validated_config = form_config;
+            }
+
             ui.horizontal(|ui| {
                 let save_config_button_handle = ui.add_enabled(
                     my_app.is_form_valid,
@@ -234,26 +573,171 @@ fn ui_add_config(ui: &mut Ui, my_app: &mut MyApp) {
                 );
 
                 if save_config_button_handle.clicked() {
-                    my_app.config.save_app_config();
+                    my_app.config.save_app_config(&my_app.config_overrides);
                 }
 
                 let reset_button_handle = ui.add(egui::Button::new("Reset configuration"));
 
                 if reset_button_handle.clicked() {
-                    my_app.config = AppConfig::default();
-                    my_app.autoupdate_interval_input_string = my_app
-                        .config
-                        .auto_update_interval
-                        .lock()
-                        .unwrap()
-                        .to_string();
+                    my_app.config.apply(
+                        &my_app
+                            .config_overrides
+                            .apply(&AppConfig::default())
+                            .unwrap_or_else(|_| AppConfig::default()),
+                    );
+                    my_app.autoupdate_interval_input_string =
+                        format_duration(*my_app.config.auto_update_interval.lock().unwrap());
+                }
+            });
+        });
+
+    ui.add_space(15.0);
+}
+
+// Presets replace the source settings of the configuration, which is saved as usual
+fn ui_add_presets(ui: &mut Ui, my_app: &mut MyApp) {
+    let is_configurable = !*my_app.config.is_auto_update_active.lock().unwrap();
+
+    egui::CollapsingHeader::new("Presets")
+        .default_open(false)
+        .enabled(is_configurable)
+        .show(ui, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Search:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut my_app.preset_search_string)
+                        .hint_text("name, location, credit or source type")
+                        .desired_width(300.0),
+                );
+            });
+
+            let mut selected_preset: Option<Preset> = None;
+
+            egui::ScrollArea::vertical()
+                .max_height(200.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("preset_grid")
+                        .num_columns(6)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for preset in my_app
+                                .presets
+                                .iter()
+                                .filter(|preset| preset.matches(&my_app.preset_search_string))
+                            {
+                                ui.label(preset.name.as_str()).on_hover_text(format!(
+                                    "Credit: {}\nFrom {}",
+                                    preset.credit, preset.catalog
+                                ));
+                                ui.label(preset.location.as_str());
+                                ui.label(preset.kind());
+                                ui.label(preset.recommended_interval.as_str());
+                                ui.label(preset.timezone.as_str());
+
+                                if ui.button("Use").clicked() {
+                                    selected_preset = Some(preset.clone());
+                                }
+
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            if let Some(preset) = selected_preset {
+                let apply_result = preset.apply_to(&my_app.config, &my_app.config_overrides);
+
+                my_app.preset_status = match apply_result {
+                    Ok(_) => {
+                        my_app.autoupdate_interval_input_string =
+                            format_duration(*my_app.config.auto_update_interval.lock().unwrap());
+                        format!("{} applied, save the configuration to keep it", preset.name)
+                    }
+                    Err(e) => {
+                        log::error!("Preset could not be applied - {}", e);
+                        e
+                    }
+                };
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Catalog file:");
+                ui.add(
+                    egui::TextEdit::singleline(&mut my_app.preset_catalog_input_string)
+                        .desired_width(300.0),
+                );
+
+                if ui.button("Load").clicked() {
+                    let catalog_location = my_app.preset_catalog_input_string.trim().to_string();
+
+                    my_app.preset_status =
+                        match load_preset_catalog(Path::new(&catalog_location)) {
+                            Ok(preset_catalog) => {
+                                let preset_catalogs_handle =
+                                    &mut *my_app.config.preset_catalogs.lock().unwrap();
+                                if !preset_catalogs_handle.contains(&catalog_location) {
+                                    preset_catalogs_handle.push(catalog_location.clone());
+                                }
+                                my_app.preset_catalog_input_string.clear();
+
+                                format!(
+                                    "{} presets loaded from {}, save the configuration to keep it",
+                                    preset_catalog.presets.len(),
+                                    catalog_location
+                                )
+                            }
+                            Err(e) => e,
+                        };
+
+                    (my_app.presets, my_app.preset_catalog_errors) =
+                        load_preset_catalogs(&my_app.config);
                 }
             });
+
+            for preset_catalog_error in &my_app.preset_catalog_errors {
+                ui.label(
+                    RichText::new(format!("- {}", preset_catalog_error))
+                        .color(ui.visuals().error_fg_color),
+                );
+            }
+
+            if !my_app.preset_status.is_empty() {
+                ui.label(my_app.preset_status.as_str());
+            }
         });
 
     ui.add_space(15.0);
 }
 
+// Overridden keys (environment, command line) are shown read-only, fields with validation
+// issues are highlighted. Returns whether the key is overridden.
+pub fn ui_add_config_label(ui: &mut Ui, my_app: &MyApp, text: &str, key: &str) -> bool {
+    let origin = my_app.config_overrides.origin_of(key);
+    let mut label_text = RichText::new(match origin {
+        Some(_) => format!("{} (overridden)", text),
+        None => text.to_string(),
+    });
+    let mut hover_texts: Vec<String> = Vec::new();
+
+    if let Some(origin) = origin {
+        hover_texts.push(format!("Set by {}, read-only here", origin));
+    }
+
+    for issue in issues_for_field(&my_app.config_issues, key) {
+        label_text = label_text.color(match issue.severity {
+            ValidationSeverity::Error => ui.visuals().error_fg_color,
+            ValidationSeverity::Warning => ui.visuals().warn_fg_color,
+        });
+        hover_texts.push(issue.message.clone());
+    }
+
+    let label_handle = ui.label(label_text);
+    if !hover_texts.is_empty() {
+        label_handle.on_hover_text(hover_texts.join("\n"));
+    }
+
+    origin.is_some()
+}
+
 fn ui_add_controls(ui: &mut Ui, my_app: &mut MyApp) {
     egui::CollapsingHeader::new("Controls")
         .default_open(true)
@@ -282,8 +766,15 @@ fn ui_add_controls(ui: &mut Ui, my_app: &mut MyApp) {
             });
 
             if !my_app.is_form_valid {
-                let validation_message = format!("Auto-update cannot be started, the configuration is incorrect.\n1. The auto-update interval must be divisible by {} seconds\n2. The image address must be a valid http or https URL directly pointing to an image\n3. Supported image formats / suffixes: .jpg, .jpeg, .png", AppConstants::AUTO_UPDATE_MIN_INTERVAL);
-                ui.label(validation_message);
+                ui.label("Auto-update cannot be started, the configuration is incorrect:");
+            }
+
+            for issue in &my_app.config_issues {
+                let color = match issue.severity {
+                    ValidationSeverity::Error => ui.visuals().error_fg_color,
+                    ValidationSeverity::Warning => ui.visuals().warn_fg_color,
+                };
+                ui.label(RichText::new(format!("- {}", issue.message)).color(color));
             }
         });
 
@@ -370,3 +861,69 @@ fn ui_add_test_image(my_app: &mut MyApp, ctx: &Context) {
             );
         });
 }
+
+// Shown below the test image window, with the same visibility
+fn ui_add_image_metadata(my_app: &mut MyApp, ctx: &Context) {
+    let image_metadata = my_app.last_image_metadata.lock().unwrap().clone();
+    let caption_template = my_app.config.caption_template.lock().unwrap().clone();
+
+    let or_not_available = |value: String| {
+        if value.is_empty() {
+            String::from("N/A")
+        } else {
+            value
+        }
+    };
+
+    egui::Window::new("Image metadata")
+        .collapsible(false)
+        .open(&mut my_app.is_diagnostic_image_shown)
+        .anchor(Align2::RIGHT_TOP, [-10.0, 420.0])
+        .show(ctx, |ui| {
+            egui::Grid::new("image_metadata_grid")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    let rows = [
+                        ("Title:", image_metadata.title.clone()),
+                        ("Credit:", image_metadata.credit.clone()),
+                        (
+                            "Capture time:",
+                            image_metadata
+                                .capture_time
+                                .map(|capture_time| {
+                                    capture_time.format("%Y-%m-%d %H:%M:%S").to_string()
+                                })
+                                .unwrap_or_default(),
+                        ),
+                        ("Camera:", image_metadata.camera.clone()),
+                        (
+                            "GPS position:",
+                            image_metadata
+                                .gps_position
+                                .map(|(latitude, longitude)| {
+                                    format!("{:.5}, {:.5}", latitude, longitude)
+                                })
+                                .unwrap_or_default(),
+                        ),
+                        ("Last modified:", image_metadata.cache_validators.last_modified.clone()),
+                    ];
+
+                    for (label, value) in rows {
+                        ui.label(label);
+                        ui.label(or_not_available(value));
+
+                        ui.end_row();
+                    }
+
+                    if !caption_template.is_empty() {
+                        ui.label("Caption:");
+                        ui.label(
+                            expand_caption_template(&caption_template, &image_metadata)
+                                .unwrap_or_else(|e| e),
+                        );
+
+                        ui.end_row();
+                    }
+                });
+        });
+}
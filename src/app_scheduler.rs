@@ -0,0 +1,87 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    app_gui::MyApp,
+    common_utils::{fetch_pic, reload_config_if_changed},
+    control_api::start_control_server,
+    http_server::start_http_server,
+    log_utils::apply_logging_config,
+    metrics::write_metrics_textfile,
+    single_instance::{handle_handoff_request, take_handoff_requests},
+    windows_os_utils::set_pic_as_wallpaper,
+};
+
+// Updates are timed from the start of the previous update rather than counted in ticks, the
+// tick only bounds how long GUI requests (test fetch, config changes) and requests handed over
+// by another launch or the control API wait to be picked up
+pub fn start_scheduler(mut my_app: MyApp) {
+    let mut config_file_modified_time = AppConfig::config_file_modified_time();
+    let mut last_update_time: Option<Instant> = None;
+
+    start_control_server(my_app.clone());
+    start_http_server(my_app.clone());
+
+    thread::spawn(move || loop {
+        log::info!("Timer tick");
+
+        let queued_requests = std::mem::take(&mut *my_app.queued_requests.lock().unwrap());
+        for request in queued_requests.into_iter().chain(take_handoff_requests()) {
+            handle_handoff_request(&mut my_app, request);
+        }
+
+        reload_config_if_changed(&my_app, &mut config_file_modified_time);
+        apply_logging_config(&my_app.config.logging.lock().unwrap());
+
+        let mut sleep_duration = Duration::from_secs(AppConstants::TICK_INTERVAL);
+
+        if *my_app.config.is_auto_update_active.lock().unwrap() {
+            let auto_update_interval = *my_app.config.auto_update_interval.lock().unwrap();
+
+            let is_update_due = match last_update_time {
+                Some(last_update_time) => last_update_time.elapsed() >= auto_update_interval,
+                None => true,
+            };
+
+            if is_update_due {
+                log::info!("Auto-update cycle started");
+                last_update_time = Some(Instant::now());
+                // a failed fetch keeps the previous wallpaper
+                if fetch_pic(&mut my_app) {
+                    set_pic_as_wallpaper(&my_app);
+                }
+            }
+
+            if let Some(last_update_time) = last_update_time {
+                let time_to_next_update =
+                    auto_update_interval.saturating_sub(last_update_time.elapsed());
+                sleep_duration = sleep_duration.min(time_to_next_update);
+                *my_app.next_update_time.lock().unwrap() = chrono::Duration::from_std(
+                    time_to_next_update,
+                )
+                .ok()
+                .map(|time_to_next_update| Local::now() + time_to_next_update);
+            }
+
+            *my_app.status.lock().unwrap() = String::from("Idle");
+        } else {
+            last_update_time = None;
+            *my_app.next_update_time.lock().unwrap() = None;
+        }
+
+        if *my_app.is_test_image_fetch_requested.lock().unwrap() {
+            fetch_pic(&mut my_app);
+            *my_app.is_test_image_fetch_requested.lock().unwrap() = false;
+        }
+
+        write_metrics_textfile(&my_app);
+
+        thread::sleep(sleep_duration);
+    });
+}
@@ -0,0 +1,367 @@
+use std::{
+    io::Read,
+    os::windows::process::CommandExt,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use chrono::Local;
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    app_gui::{ui_add_config_label, MyApp},
+    config_overrides::ConfigOverrides,
+    config_validation::{error, ValidationIssue},
+    fetch_result::{FetchError, ImageMetadata},
+    image_source::{FetchContext, FetchedImage, ImageSource},
+    local_folder_source::read_image_file,
+    url_template::expand_plain_template,
+    windows_os_utils::get_screen_resolution,
+};
+
+// keeps console programs from flashing a window on every fetch
+const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+// Only the end of the error output is kept for the Status panel, where the actual error
+// message usually is
+const MAX_STDERR_SUMMARY_LENGTH: usize = 300;
+const MAX_STDERR_SIZE: u64 = 64 * 1024;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum CommandOutput {
+    #[default]
+    Stdout,
+    PrintedPath,
+}
+
+// Settings of commands generating the image (e.g. a rendered dashboard or chart). Stdout reads
+// the image from the command's output, PrintedPath reads the file at the last line it printed.
+// The name is passed to the command, so one script can serve several sources.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommandSource {
+    pub arguments: Vec<String>,
+    pub output: CommandOutput,
+    pub timeout_seconds: u64,
+    pub working_directory: String,
+    pub name: String,
+}
+
+impl Default for CommandSource {
+    fn default() -> Self {
+        Self {
+            arguments: Vec::new(),
+            output: CommandOutput::Stdout,
+            timeout_seconds: 60,
+            working_directory: String::from(""),
+            name: String::from(""),
+        }
+    }
+}
+
+// Runs the command at the image address and reads the image it generated
+pub struct Command;
+
+impl ImageSource for Command {
+    fn kind(&self) -> &'static str {
+        "Command"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "Command:"
+    }
+
+    fn is_address_url(&self) -> bool {
+        false
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        let command_source = context.config.command.lock().unwrap().clone();
+        let max_download_size = context.config.download_limits.lock().unwrap().max_download_size;
+
+        let arguments = command_source
+            .arguments
+            .iter()
+            .map(|argument| expand_plain_template(argument))
+            .collect::<Result<Vec<String>, String>>()
+            .map_err(FetchError::InvalidAddress)?;
+
+        let mut command = process::Command::new(&context.source_address);
+        command
+            .args(&arguments)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .creation_flags(CREATE_NO_WINDOW);
+
+        if !command_source.working_directory.is_empty() {
+            command.current_dir(&command_source.working_directory);
+        }
+
+        // the passphrase of the secrets store and the config overrides of this app are not
+        // meant for the command, the variables below are set again for it
+        for (name, _) in std::env::vars_os() {
+            if name
+                .to_string_lossy()
+                .starts_with(ConfigOverrides::ENVIRONMENT_VARIABLE_PREFIX)
+            {
+                command.env_remove(name);
+            }
+        }
+        command.env_remove(AppConstants::SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE);
+
+        let (screen_width, screen_height) = get_screen_resolution();
+        command
+            .env("PICS2WALL_SCREEN_WIDTH", screen_width.to_string())
+            .env("PICS2WALL_SCREEN_HEIGHT", screen_height.to_string())
+            .env("PICS2WALL_SOURCE_NAME", &command_source.name)
+            .env(
+                "PICS2WALL_PREVIOUS_IMAGE",
+                previous_image_path(context.config)
+                    .map(|path| path.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+            );
+
+        log::info!("Running image command {} {}", context.source_address, arguments.join(" "));
+
+        let mut child = command.spawn().map_err(|e| {
+            FetchError::CommandFailed(
+                None,
+                format!("{} could not be started - {}", context.source_address, e),
+            )
+        })?;
+
+        let stdout_reader = read_to_limit(child.stdout.take().unwrap(), max_download_size);
+        let stderr_reader = read_to_limit(child.stderr.take().unwrap(), MAX_STDERR_SIZE);
+
+        let deadline = Instant::now() + Duration::from_secs(command_source.timeout_seconds);
+        let exit_status = loop {
+            match child.try_wait() {
+                Ok(Some(exit_status)) => break exit_status,
+                Ok(None) if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    log::error!(
+                        "Image command killed after {} seconds",
+                        command_source.timeout_seconds
+                    );
+                    return Err(FetchError::CommandTimedOut(command_source.timeout_seconds));
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(50)),
+                Err(e) => return Err(FetchError::CommandFailed(None, e.to_string())),
+            }
+        };
+
+        let (stdout, is_stdout_too_large) =
+            receive_output(&stdout_reader, deadline, command_source.timeout_seconds)?;
+        let (stderr, _) = receive_output(&stderr_reader, deadline, command_source.timeout_seconds)?;
+        let stderr = String::from_utf8_lossy(&stderr).trim().to_string();
+
+        if !exit_status.success() {
+            log::error!(
+                "Image command exited with code {:?}, error output:\n{}",
+                exit_status.code(),
+                stderr
+            );
+            return Err(FetchError::CommandFailed(
+                exit_status.code(),
+                summarize_stderr(&stderr),
+            ));
+        }
+
+        if !stderr.is_empty() {
+            log::warn!("Image command error output:\n{}", stderr);
+        }
+
+        let data = match command_source.output {
+            CommandOutput::Stdout if is_stdout_too_large => {
+                return Err(FetchError::TooLarge(max_download_size));
+            }
+            CommandOutput::Stdout => stdout,
+            CommandOutput::PrintedPath => {
+                let printed_path = String::from_utf8_lossy(&stdout)
+                    .lines()
+                    .map(|line| line.trim().to_string())
+                    .rfind(|line| !line.is_empty())
+                    .ok_or_else(|| {
+                        FetchError::ImageNotFound(String::from("the command printed no path"))
+                    })?;
+
+                let mut image_path = PathBuf::from(&command_source.working_directory);
+                image_path.push(printed_path);
+
+                log::info!("Image command printed the path {}", image_path.display());
+
+                read_image_file(&image_path, max_download_size)?
+            }
+        };
+
+        Ok(Some(FetchedImage {
+            data,
+            metadata: ImageMetadata {
+                capture_time: Some(Local::now()),
+                ..ImageMetadata::default()
+            },
+        }))
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        let command_source = config.command.lock().unwrap().clone();
+
+        if config.image_address.lock().unwrap().trim().is_empty() {
+            issues.push(error("image_address", "A command is required"));
+        }
+
+        if command_source.timeout_seconds == 0 {
+            issues.push(error("command.timeout_seconds", "The timeout must be at least 1 second"));
+        }
+
+        if !command_source.working_directory.is_empty()
+            && !Path::new(&command_source.working_directory).is_dir()
+        {
+            issues.push(error(
+                "command.working_directory",
+                &format!("{} is not a folder", command_source.working_directory),
+            ));
+        }
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, desired_width: f32) {
+        let command_handle = &mut *my_app.config.command.lock().unwrap();
+
+        if !command_handle.arguments.is_empty() {
+            ui_add_config_label(ui, my_app, "Arguments:", "command.arguments");
+            ui.label(command_handle.arguments.join("\n"))
+                .on_hover_text("Arguments can be edited in the config file");
+
+            ui.end_row();
+        }
+
+        let is_overridden = ui_add_config_label(ui, my_app, "Image from:", "command.output");
+
+        ui.add_enabled_ui(!is_overridden, |ui| {
+            egui::ComboBox::from_id_source("command_output")
+                .selected_text(format!("{:?}", command_handle.output))
+                .show_ui(ui, |ui| {
+                    for output in [CommandOutput::Stdout, CommandOutput::PrintedPath] {
+                        ui.selectable_value(
+                            &mut command_handle.output,
+                            output,
+                            format!("{:?}", output),
+                        );
+                    }
+                });
+        });
+
+        ui.end_row();
+
+        let is_overridden =
+            ui_add_config_label(ui, my_app, "Timeout (s):", "command.timeout_seconds");
+        ui.add_enabled(
+            !is_overridden,
+            egui::DragValue::new(&mut command_handle.timeout_seconds).clamp_range(1..=3600),
+        );
+
+        ui.end_row();
+
+        let is_overridden =
+            ui_add_config_label(ui, my_app, "Working folder:", "command.working_directory");
+        ui.add_enabled(
+            !is_overridden,
+            egui::TextEdit::singleline(&mut command_handle.working_directory)
+                .desired_width(desired_width),
+        );
+
+        ui.end_row();
+
+        let is_overridden = ui_add_config_label(ui, my_app, "Source name:", "command.name");
+        ui.add_enabled(
+            !is_overridden,
+            egui::TextEdit::singleline(&mut command_handle.name).desired_width(desired_width),
+        )
+        .on_hover_text("Passed to the command as PICS2WALL_SOURCE_NAME");
+
+        ui.end_row();
+    }
+}
+
+fn previous_image_path(config: &AppConfig) -> Option<PathBuf> {
+    if config.file_type.is_empty() {
+        return None;
+    }
+
+    let path = PathBuf::from(
+        AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string() + "current." + &config.file_type,
+    );
+
+    std::fs::canonicalize(path).ok()
+}
+
+// Pipes are drained on their own threads, so a command writing more than the pipe buffer holds
+// does not block. Data beyond the limit is dropped and reported.
+fn read_to_limit(mut reader: impl Read + Send + 'static, limit: u64) -> Receiver<(Vec<u8>, bool)> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut data = Vec::new();
+        let mut is_too_large = false;
+        let mut buffer = [0; 8192];
+
+        loop {
+            match reader.read(&mut buffer) {
+                Ok(0) | Err(_) => break,
+                Ok(read_size) if (data.len() + read_size) as u64 > limit => is_too_large = true,
+                Ok(read_size) => data.extend_from_slice(&buffer[..read_size]),
+            }
+        }
+
+        let _ = sender.send((data, is_too_large));
+    });
+
+    receiver
+}
+
+// Processes started by the command inherit its pipes and can keep them open after the command
+// exited, so the output is only waited for until the deadline
+fn receive_output(
+    output_receiver: &Receiver<(Vec<u8>, bool)>,
+    deadline: Instant,
+    timeout_seconds: u64,
+) -> Result<(Vec<u8>, bool), FetchError> {
+    match output_receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => Ok(output),
+        Err(RecvTimeoutError::Timeout) => {
+            log::error!(
+                "Image command output still open after {} seconds, a process started by the \
+                 command may hold it",
+                timeout_seconds
+            );
+            Err(FetchError::CommandTimedOut(timeout_seconds))
+        }
+        Err(RecvTimeoutError::Disconnected) => Ok((Vec::new(), false)),
+    }
+}
+
+fn summarize_stderr(stderr: &str) -> String {
+    if stderr.is_empty() {
+        return String::from("no error output");
+    }
+
+    let char_count = stderr.chars().count();
+    if char_count <= MAX_STDERR_SUMMARY_LENGTH {
+        return stderr.to_string();
+    }
+
+    let summary: String = stderr
+        .chars()
+        .skip(char_count - MAX_STDERR_SUMMARY_LENGTH)
+        .collect();
+
+    format!("...{}", summary)
+}
@@ -1,14 +1,15 @@
 use std::{
+    cell::OnceCell,
     fs::File,
     io::Write,
-    path::{Path},
+    path::{Path, PathBuf},
+    time::{Instant, SystemTime},
 };
 
 use chrono::Local;
-use curl::easy::Easy;
 use egui::ColorImage;
 use egui_extras::RetainedImage;
-use image::ImageError;
+use image::{io::Limits, DynamicImage, ImageError};
 
 use winapi::{
     um::winuser::{
@@ -18,7 +19,18 @@ use winapi::{
 
 
 use crate::app_gui::MyApp;
-use crate::{app_constants::AppConstants};
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    config_format_utils::{active_profile, config_file_exists, set_active_profile},
+    exif_utils::{apply_orientation, read_exif_details},
+    fetch_result::{FetchError, FetchHistoryEntry, FetchResult, ImageMetadata},
+    image_conversion::{encode_image, wallpaper_format},
+    image_source::{find_image_source, image_source_kinds, FetchContext},
+    image_validation::decode_and_validate_image,
+    source_auth::{mask_url_credentials, strip_url_query},
+    url_template::{expand_plain_template, expand_url_template},
+};
 
 pub fn color32_to_reversed_u32(color: [u8; 3]) -> u32 {
     let hex_string = format!("{:02X}{:02X}{:02X}", color[2], color[1], color[0]);
@@ -36,161 +48,374 @@ pub fn get_current_background_color() -> [u8; 3] {
     }
 }
 
-pub fn load_image_from_path(path: &std::path::Path) -> Result<ColorImage, ImageError> {
-    let image_file_open = image::io::Reader::open(path);
+pub fn load_image_from_path(path: &std::path::Path, limits: Limits) -> Result<ColorImage, ImageError> {
+    let mut image_reader = image::io::Reader::open(path)?;
+    image_reader.limits(limits);
+
+    let image = image_reader.decode()?;
 
-    let image = image_file_open.unwrap().decode()?;
+    Ok(dynamic_image_to_color_image(&image))
+}
 
+pub fn dynamic_image_to_color_image(image: &DynamicImage) -> ColorImage {
     let size = [image.width() as _, image.height() as _];
     let image_buffer = image.to_rgba8();
     let pixels = image_buffer.as_flat_samples();
-    Ok(egui::ColorImage::from_rgba_unmultiplied(
+    egui::ColorImage::from_rgba_unmultiplied(
         size,
         pixels.as_slice(),
-    ))
+    )
 }
 
-pub fn fetch_pic(my_app: &mut MyApp) {
-    //TODO: Structure better
-
-    let mut is_fetch_successful = false;
-
+// Returns whether a new, validated image was stored as the current wallpaper image. On failure
+// the previous wallpaper image is kept and only the test image shows the failure.
+pub fn fetch_pic(my_app: &mut MyApp) -> bool {
     log::info!(
         "Fetching image from {}",
-        &my_app.config.image_address.lock().unwrap()
+        mask_url_credentials(&my_app.config.image_address.lock().unwrap())
+    );
+
+    set_test_image_from_resource(my_app, AppConstants::IN_PROGRESS_TEST_IMAGE_FILE_LOCATION);
+
+    *my_app.status.lock().unwrap() = String::from("Fetching");
+
+    let fetch_start_time = Instant::now();
+    let mut downloaded_bytes = 0;
+    let image_address = my_app.config.image_address.lock().unwrap().clone();
+    let kind = my_app.config.kind.lock().unwrap().clone();
+    let download_limits = my_app.config.download_limits.lock().unwrap().clone();
+    let image_requirements = my_app.config.image_requirements.lock().unwrap().clone();
+    let image_conversion = my_app.config.image_conversion.lock().unwrap().clone();
+
+    let fetch_result = find_image_source(&kind)
+        .ok_or_else(|| FetchError::UnknownSourceKind(kind.clone()))
+        .and_then(|image_source| {
+            let context = FetchContext {
+                config: &my_app.config,
+                source_address: if image_source.is_address_url() {
+                    expand_url_template(&image_address)
+                } else {
+                    expand_plain_template(&image_address)
+                }
+                .map_err(FetchError::InvalidAddress)?,
+                previous_metadata: ImageMetadata {
+                    cache_validators: my_app.wallpaper_validators.lock().unwrap().applied().clone(),
+                    ..my_app.last_image_metadata.lock().unwrap().clone()
+                },
+                resolved_credentials: OnceCell::new(),
+            };
+
+            let fetched_image = match image_source.fetch(&context)? {
+                Some(fetched_image) => fetched_image,
+                None => return Ok(None),
+            };
+
+            downloaded_bytes = fetched_image.data.len() as u64;
+
+            let decode_start_time = Instant::now();
+            let decode_result = decode_and_validate_image(
+                &fetched_image.data,
+                &download_limits,
+                &image_requirements,
+                &image_conversion,
+            );
+            my_app.metrics.lock().unwrap().record_decode(decode_start_time.elapsed());
+            let (image, image_format) = decode_result?;
+            let (stored_format, file_type) = wallpaper_format(image_format);
+
+            if stored_format != image_format {
+                log::info!("{:?} image converted to {:?}", image_format, stored_format);
+            }
+
+            // Windows ignores the EXIF orientation of wallpapers, so rotated images are stored
+            // upright
+            let exif_details = read_exif_details(&fetched_image.data);
+            let image = apply_orientation(image, exif_details.orientation);
+            let image_data = if exif_details.orientation == 1 && stored_format == image_format {
+                fetched_image.data
+            } else {
+                encode_image(&image, stored_format)?
+            };
+            let image_metadata = exif_details.merge_into(fetched_image.metadata);
+
+            write_file_atomically(
+                Path::new(
+                    &(AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
+                        + "current."
+                        + file_type),
+                ),
+                image_data.as_slice(),
+            )
+            .map_err(|e| FetchError::Transfer(format!("image could not be stored ({})", e)))?;
+
+            image_source.on_image_applied(&context, &image_metadata);
+
+            Ok(Some((image, file_type, image_metadata)))
+        });
+
+    *my_app.last_fetch_time.lock().unwrap() =
+        Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let is_fetch_successful = match fetch_result {
+        Ok(Some((image, file_type, image_metadata))) => {
+            my_app.config.file_type = file_type.to_string();
+            my_app
+                .wallpaper_validators
+                .lock()
+                .unwrap()
+                .record_stored(image_metadata.cache_validators.clone());
+            *my_app.last_image_metadata.lock().unwrap() = image_metadata;
+            *my_app.test_image.lock().unwrap() =
+                RetainedImage::from_color_image("test_image", dynamic_image_to_color_image(&image));
+            *my_app.last_fetch_result.lock().unwrap() = FetchResult::Succeeded;
+            log::info!("Image saved");
+            true
+        }
+        Ok(None) => {
+            log::info!("Image not modified since the previous fetch, keeping the wallpaper");
+            set_test_image_from_path(
+                my_app,
+                Path::new(
+                    &(AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
+                        + "current."
+                        + &my_app.config.file_type),
+                ),
+            );
+            *my_app.last_fetch_result.lock().unwrap() = FetchResult::NotModified;
+            false
+        }
+        Err(fetch_error) => {
+            log::error!("Could not fetch image, keeping the previous wallpaper - {}", fetch_error);
+            set_test_image_from_resource(my_app, AppConstants::FAILED_TEST_IMAGE_FILE_LOCATION);
+            *my_app.last_fetch_result.lock().unwrap() = FetchResult::Failed(fetch_error);
+            false
+        }
+    };
+
+    let fetch_duration = fetch_start_time.elapsed();
+    // the address without query string, API keys would otherwise end up in the metric labels
+    let source_label = strip_url_query(&mask_url_credentials(&image_address));
+
+    my_app.metrics.lock().unwrap().record_fetch(
+        &kind,
+        &source_label,
+        &my_app.last_fetch_result.lock().unwrap(),
+        downloaded_bytes,
+        fetch_duration,
     );
 
-    set_test_image_from_path(
+    record_fetch_history(
         my_app,
-        Path::new(&(AppConstants::IN_PROGRESS_TEST_IMAGE_FILE_LOCATION)),
+        FetchHistoryEntry {
+            time: my_app.last_fetch_time.lock().unwrap().clone(),
+            kind,
+            image_address: source_label,
+            result: my_app.last_fetch_result.lock().unwrap().to_string(),
+            duration_ms: fetch_duration.as_millis() as u64,
+        },
     );
 
-    let mut file_type = "unknown";
-    let binding = &my_app.config.image_address.lock().unwrap().clone()[..];
+    *my_app.status.lock().unwrap() = String::from("Idle");
 
-    if binding.ends_with("png") {
-        file_type = "png";
-    } else if binding.ends_with("jpg") || binding.ends_with("jpeg") {
-        file_type = "jpg";
+    is_fetch_successful
+}
+
+fn record_fetch_history(my_app: &MyApp, fetch_history_entry: FetchHistoryEntry) {
+    let fetch_history_handle = &mut *my_app.fetch_history.lock().unwrap();
+
+    if fetch_history_handle.len() >= AppConstants::FETCH_HISTORY_LENGTH {
+        fetch_history_handle.pop_front();
     }
+    fetch_history_handle.push_back(fetch_history_entry);
+}
 
-    *my_app.status.lock().unwrap() = String::from("Fetching");
+// JPEG images are stored as current.jpg and every other format as current.png, the newer one
+// is the current wallpaper image
+pub fn current_wallpaper_image_location() -> Option<PathBuf> {
+    ["jpg", "png"]
+        .iter()
+        .map(|file_type| {
+            PathBuf::from(
+                AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string() + "current." + file_type,
+            )
+        })
+        .filter_map(|location| {
+            let modified_time = std::fs::metadata(&location)
+                .and_then(|metadata| metadata.modified())
+                .ok()?;
+            Some((modified_time, location))
+        })
+        .max()
+        .map(|(_, location)| location)
+}
+
+// Bundled images in resources\ are decoded without the configured limits, those only guard
+// against fetched images
+pub fn load_resource_image(path: &Path) -> Result<ColorImage, ImageError> {
+    load_image_from_path(path, Limits::default())
+}
 
-    let mut easy = Easy::new();
+// Shows the image in the test image window, falling back to the failed fetch status image
+// when it cannot be loaded. The current wallpaper image is never touched here.
+pub fn set_test_image_from_path(my_app: &MyApp, path: &Path) {
+    let image_limits = my_app.config.download_limits.lock().unwrap().image_limits();
+    let img = load_image_from_path(path, image_limits).or_else(|_| {
+        load_resource_image(Path::new(AppConstants::FAILED_TEST_IMAGE_FILE_LOCATION))
+    });
 
-    let url_set_result = easy.url(&my_app.config.image_address.lock().unwrap());
-    match url_set_result {
-        Ok(_) => {}
-        Err(_) => {
-            log::error!("Setting URL for the fetch handle failed");
+    match img {
+        Ok(img) => {
+            *my_app.test_image.lock().unwrap() = RetainedImage::from_color_image("test_image", img);
         }
+        Err(_) => log::error!("Test image {:?} could not be loaded", path),
     }
+}
 
-    let redirect = easy.follow_location(true);
-    match redirect {
-        Ok(_) => {}
-        Err(_) => log::error!("Setting redirect allowed failed"),
+// Shows one of the bundled status images in the test image window
+pub fn set_test_image_from_resource(my_app: &MyApp, location: &str) {
+    match load_resource_image(Path::new(location)) {
+        Ok(img) => {
+            *my_app.test_image.lock().unwrap() = RetainedImage::from_color_image("test_image", img);
+        }
+        Err(_) => log::error!("Test image {} could not be loaded", location),
     }
+}
 
-    let mut dst = Vec::new();
-    {
-        let mut transfer = easy.transfer();
-        transfer
-            .write_function(|data| {
-                dst.extend_from_slice(data);
-                Ok(data.len())
-            })
-            .expect("Setting write function for transfer failed");
-
-        let transfer_result = transfer.perform();
-
-        *my_app.last_fetch_time.lock().unwrap() =
-            Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-        match transfer_result {
-            Ok(_) => {
-                is_fetch_successful = true;
-                *my_app.last_fetch_result.lock().unwrap() = "Succeeded".to_string();
-
-                AppConstants::WALLPAPER_IMAGE_FILE_LOCATION
-            }
-            Err(_) => {
-                log::error!("Could not fetch image");
-                *my_app.last_fetch_result.lock().unwrap() = "Failed".to_string();
-                AppConstants::FAILED_TEST_IMAGE_FILE_LOCATION
-            }
-        };
+// Writes into a temporary file next to the target, flushes it to disk and renames it over
+// the target, so readers only ever see the previous or the complete new content
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Missing file name"))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let write_result = File::create(&temp_path).and_then(|mut temp_file| {
+        temp_file.write_all(contents)?;
+        temp_file.sync_all()
+    });
+
+    match write_result.and_then(|_| std::fs::rename(&temp_path, path)) {
+        Ok(_) => Ok(()),
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            Err(e)
+        }
     }
+}
+
+pub fn reload_config_if_changed(my_app: &MyApp, last_seen_modified_time: &mut Option<SystemTime>) {
+    let modified_time = AppConfig::config_file_modified_time();
 
-    if is_fetch_successful {
-        let mut file = File::create(
-            AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string() + "current." + file_type,
-        )
-        .unwrap();
-        my_app.config.file_type = file_type.to_string();
-        file.write_all(dst.as_slice()).unwrap();
-    } else {
-        my_app.config.file_type = "jpg".to_string();
-        std::fs::copy(
-            AppConstants::FAILED_TEST_IMAGE_FILE_LOCATION,
-            AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
-                + "current."
-                + &my_app.config.file_type,
-        )
-        .expect("Could not copy failed test image as current image");
+    if modified_time.is_none() || modified_time == *last_seen_modified_time {
+        return;
     }
 
-    set_test_image_from_path(
-        my_app,
-        Path::new(
-            &(AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
-                + "current."
-                + &my_app.config.file_type),
-        ),
-    );
+    *last_seen_modified_time = modified_time;
 
-    *my_app.status.lock().unwrap() = String::from("Idle");
+    let reload_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
-    log::info!("Image saved");
+    match AppConfig::try_load_app_config(&my_app.config_overrides) {
+        Ok(reloaded_config) => {
+            my_app.config.apply(&reloaded_config);
+            *my_app.config_generation.lock().unwrap() += 1;
+            *my_app.config_reload_status.lock().unwrap() = format!("Applied ({})", reload_time);
+            log::info!("Config file change detected and applied");
+        }
+        Err(e) => {
+            *my_app.config_reload_status.lock().unwrap() =
+                format!("Rejected ({}): {}", reload_time, e);
+            log::error!("Config file change rejected, keeping current configuration - {}", e);
+        }
+    }
 }
 
-pub fn is_image_url_valid(url: &str) -> bool {
-    if (url.starts_with("http") || url.starts_with("https"))
-        && url.contains("://")
-        && (url.ends_with(".jpg") || url.ends_with(".jpeg") || url.ends_with(".png"))
-    {
-        true
-    } else {
-        false
+// Checks that can be answered before the switch is queued, the rest of the config is
+// validated when the switch is applied
+pub fn check_source_switch(my_app: &MyApp, kind: &str) -> Result<(), String> {
+    if find_image_source(kind).is_none() {
+        return Err(format!(
+            "Unknown source type {}, supported are {}",
+            kind,
+            image_source_kinds().join(", ")
+        ));
+    }
+
+    for key in ["kind", "image_address"] {
+        if let Some(origin) = my_app.config_overrides.origin_of(key) {
+            return Err(format!("{} is set by {} and cannot be switched", key, origin));
+        }
     }
-}
 
-pub fn set_test_image_from_path(my_app: &mut MyApp, path: &Path) {
-    let img = load_image_from_path(path);
+    Ok(())
+}
 
-    // TODO: Fix ugly
-    match img {
-        Ok(_) => {
-            *my_app.test_image.lock().unwrap() =
-                RetainedImage::from_color_image("test_image", img.unwrap());
+// Like picking a preset in the GUI the change is not saved to the config file. The current
+// source stays active when the new one is rejected.
+pub fn switch_source(my_app: &MyApp, kind: &str, image_address: &str) -> Result<(), String> {
+    let switch_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    let switch_result = check_source_switch(my_app, kind).and_then(|_| {
+        // a separate copy, clones of the config share their values
+        let source_config: AppConfig = serde_json::to_value(&my_app.config)
+            .and_then(serde_json::from_value)
+            .map_err(|e| e.to_string())?;
+        *source_config.kind.lock().unwrap() = kind.to_string();
+        *source_config.image_address.lock().unwrap() = image_address.to_string();
+
+        source_config.validate().map(|_| source_config)
+    });
+
+    let source = format!("{} {}", kind, mask_url_credentials(image_address));
+    match switch_result {
+        Ok(source_config) => {
+            my_app.config.apply(&source_config);
+            *my_app.config_generation.lock().unwrap() += 1;
+            *my_app.config_reload_status.lock().unwrap() =
+                format!("Source {} applied ({})", source, switch_time);
+            log::info!("Source switched to {}", source);
+            Ok(())
         }
-        Err(_) => {
-            my_app.config.file_type = "jpg".to_string();
-            std::fs::copy(
-                AppConstants::FAILED_TEST_IMAGE_FILE_LOCATION,
-                AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
-                    + "current."
-                    + &my_app.config.file_type,
-            )
-            .unwrap();
+        Err(e) => {
+            *my_app.config_reload_status.lock().unwrap() =
+                format!("Source {} rejected ({}): {}", source, switch_time, e);
+            log::error!("Source {} rejected, keeping current configuration - {}", source, e);
+            Err(e)
+        }
+    }
+}
 
-            let img = load_image_from_path(Path::new(
-                &(AppConstants::WALLPAPER_IMAGE_FILE_LOCATION.to_string()
-                    + "current."
-                    + &my_app.config.file_type),
+// Loads the config file of the profile, the current profile stays active when it is rejected
+pub fn switch_profile(my_app: &MyApp, profile: &str) -> Result<(), String> {
+    let switch_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let previous_profile = active_profile();
+
+    let switch_result = set_active_profile(profile).and_then(|_| {
+        if !config_file_exists() {
+            return Err(format!(
+                "Profile {} has no config file in {}",
+                profile,
+                AppConstants::PROFILES_DIRECTORY
             ));
+        }
 
-            *my_app.test_image.lock().unwrap() =
-                RetainedImage::from_color_image("test_image", img.unwrap());
+        AppConfig::try_load_app_config(&my_app.config_overrides)
+    });
+
+    match switch_result {
+        Ok(profile_config) => {
+            my_app.config.apply(&profile_config);
+            *my_app.config_generation.lock().unwrap() += 1;
+            *my_app.config_reload_status.lock().unwrap() =
+                format!("Profile {} applied ({})", profile, switch_time);
+            log::info!("Switched to profile {}", profile);
+            Ok(())
+        }
+        Err(e) => {
+            let _ = set_active_profile(&previous_profile);
+            *my_app.config_reload_status.lock().unwrap() =
+                format!("Profile {} rejected ({}): {}", profile, switch_time, e);
+            log::error!("Profile {} rejected, keeping current configuration - {}", profile, e);
+            Err(e)
         }
     }
 }
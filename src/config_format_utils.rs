@@ -0,0 +1,263 @@
+use std::{path::Path, sync::Mutex};
+
+use toml_edit::{Document, Table};
+
+use crate::{app_config::AppConfig, app_constants::AppConstants};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_ascii_lowercase())
+            .as_deref()
+        {
+            Some("toml") => ConfigFormat::Toml,
+            Some("yaml") | Some("yml") => ConfigFormat::Yaml,
+            _ => ConfigFormat::Json,
+        }
+    }
+}
+
+// A profile is an alternate config file in the profiles folder (e.g. profiles/night.toml), the
+// empty profile is the main config file
+static ACTIVE_PROFILE: Mutex<String> = Mutex::new(String::new());
+
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE.lock().unwrap().clone()
+}
+
+// The profile named default selects the main config file
+pub fn set_active_profile(profile: &str) -> Result<(), String> {
+    let profile = if profile == "default" { "" } else { profile };
+
+    // profile names become file names
+    if !profile
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Invalid profile name {}, only letters, digits, - and _ are allowed",
+            profile
+        ));
+    }
+
+    *ACTIVE_PROFILE.lock().unwrap() = profile.to_string();
+
+    Ok(())
+}
+
+// The first existing candidate wins, config.json is used when none exists yet
+pub fn find_config_file_location() -> String {
+    let profile = active_profile();
+
+    AppConstants::CONFIG_FILE_LOCATION_CANDIDATES
+        .iter()
+        .map(|candidate| profile_file_location(candidate, &profile))
+        .find(|candidate| Path::new(candidate).exists())
+        .unwrap_or_else(|| profile_file_location(AppConstants::CONFIG_FILE_LOCATION, &profile))
+}
+
+pub fn config_file_exists() -> bool {
+    Path::new(&find_config_file_location()).exists()
+}
+
+fn profile_file_location(config_file_location: &str, profile: &str) -> String {
+    if profile.is_empty() {
+        return config_file_location.to_string();
+    }
+
+    config_file_location.replacen(
+        "config",
+        &format!("{}{}", AppConstants::PROFILES_DIRECTORY, profile),
+        1,
+    )
+}
+
+pub fn deserialize_config(buffer: &str, format: ConfigFormat) -> Result<AppConfig, String> {
+    match format {
+        ConfigFormat::Json => serde_json::from_str(buffer).map_err(|e| e.to_string()),
+        ConfigFormat::Toml => toml_edit::de::from_str(buffer).map_err(|e| e.to_string()),
+        ConfigFormat::Yaml => deserialize_yaml_config(buffer),
+    }
+}
+
+// For TOML the previous file content (if any) is edited in place so that user comments,
+// key ordering and formatting survive a save from the GUI
+pub fn serialize_config(
+    config: &AppConfig,
+    format: ConfigFormat,
+    previous_content: Option<&str>,
+) -> Result<String, String> {
+    match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(config).map_err(|e| e.to_string()),
+        ConfigFormat::Toml => {
+            let updated_document =
+                toml_edit::ser::to_document(config).map_err(|e| e.to_string())?;
+
+            match previous_content.map(|content| content.parse::<Document>()) {
+                Some(Ok(mut previous_document)) => {
+                    merge_toml_preserving_format(
+                        previous_document.as_table_mut(),
+                        updated_document.as_table(),
+                    );
+                    Ok(previous_document.to_string())
+                }
+                Some(Err(_)) => {
+                    log::error!("Previous TOML config could not be parsed, formatting and comments will not be preserved");
+                    Ok(updated_document.to_string())
+                }
+                None => Ok(updated_document.to_string()),
+            }
+        }
+        ConfigFormat::Yaml => serialize_yaml_config(config),
+    }
+}
+
+// Keys the config no longer has (removed header or module level entries, aliases replaced by
+// the field name) are dropped, the remaining keys keep their comments. Nested structs and maps
+// are serialized as inline tables, tables written by hand stay tables.
+fn merge_toml_preserving_format(target: &mut Table, source: &Table) {
+    let removed_keys: Vec<String> = target
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !source.contains_key(key))
+        .collect();
+    for key in removed_keys {
+        target.remove(&key);
+    }
+
+    for (key, source_item) in source.iter() {
+        if let Some(target_item) = target.get_mut(key) {
+            if let Some(target_table) = target_item.as_table_mut() {
+                if let Ok(source_table) = source_item.clone().into_table() {
+                    merge_toml_preserving_format(target_table, &source_table);
+                    continue;
+                }
+            }
+
+            if let (Some(target_value), Some(source_value)) =
+                (target_item.as_value_mut(), source_item.as_value())
+            {
+                let decor = target_value.decor().clone();
+                *target_value = source_value.clone();
+                *target_value.decor_mut() = decor;
+                continue;
+            }
+        }
+
+        target.insert(key, source_item.clone());
+    }
+}
+
+#[cfg(feature = "yaml")]
+fn deserialize_yaml_config(buffer: &str) -> Result<AppConfig, String> {
+    serde_yaml::from_str(buffer).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn deserialize_yaml_config(_buffer: &str) -> Result<AppConfig, String> {
+    Err(String::from("YAML config support is not enabled in this build"))
+}
+
+#[cfg(feature = "yaml")]
+fn serialize_yaml_config(config: &AppConfig) -> Result<String, String> {
+    serde_yaml::to_string(config).map_err(|e| e.to_string())
+}
+
+#[cfg(not(feature = "yaml"))]
+fn serialize_yaml_config(_config: &AppConfig) -> Result<String, String> {
+    Err(String::from("YAML config support is not enabled in this build"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deserialize_config, serialize_config, ConfigFormat};
+    use crate::app_config::AppConfig;
+
+    // The default config with the auth and logging sections written as tables by hand
+    fn hand_written_toml_config() -> String {
+        let default_content =
+            serialize_config(&AppConfig::default(), ConfigFormat::Toml, None).unwrap();
+
+        default_content
+            .lines()
+            .filter(|line| !line.starts_with("source_auth = ") && !line.starts_with("logging = "))
+            .map(|line| format!("{}\n", line))
+            .collect::<String>()
+            + "\n# headers required by the image API\n"
+            + "[source_auth]\n"
+            + "method = \"None\"\n"
+            + "\n"
+            + "[source_auth.custom_headers]\n"
+            + "X-Api-Key = \"abc123\" # rotated monthly\n"
+            + "X-Client = \"pics2wall\" # sent with every request\n"
+            + "\n"
+            + "[logging.module_levels]\n"
+            + "tiny_http = \"Warn\"\n"
+            + "pics2wall = \"Debug\"\n"
+    }
+
+    #[test]
+    fn toml_save_removes_deleted_map_entries() {
+        let previous_content = hand_written_toml_config();
+        let config = deserialize_config(&previous_content, ConfigFormat::Toml).unwrap();
+        assert_eq!(config.source_auth.lock().unwrap().custom_headers.len(), 2);
+        assert_eq!(config.logging.lock().unwrap().module_levels.len(), 2);
+
+        config.source_auth.lock().unwrap().custom_headers.remove("X-Api-Key");
+        config.logging.lock().unwrap().module_levels.remove("tiny_http");
+
+        let saved_content =
+            serialize_config(&config, ConfigFormat::Toml, Some(&previous_content)).unwrap();
+        assert!(!saved_content.contains("X-Api-Key"));
+        assert!(!saved_content.contains("abc123"));
+        assert!(!saved_content.contains("tiny_http"));
+        assert!(saved_content.contains("# headers required by the image API\n[source_auth]\n"));
+        assert!(saved_content.contains("X-Client = \"pics2wall\" # sent with every request\n"));
+        assert!(saved_content.contains("[logging.module_levels]\npics2wall = \"Debug\"\n"));
+
+        let reloaded_config = deserialize_config(&saved_content, ConfigFormat::Toml).unwrap();
+        assert_eq!(
+            *reloaded_config.source_auth.lock().unwrap(),
+            *config.source_auth.lock().unwrap()
+        );
+        assert_eq!(
+            *reloaded_config.logging.lock().unwrap(),
+            *config.logging.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn toml_save_keeps_comments_and_drops_replaced_aliases() {
+        let mut config = AppConfig::default();
+        let previous_content = serialize_config(&config, ConfigFormat::Toml, None)
+            .unwrap()
+            .replace(
+                "file_type = \"\"",
+                "# set by the last fetch\nfile_type = \"\" # jpg or png",
+            )
+            .replacen("kind = ", "source_kind = ", 1);
+
+        config.file_type = String::from("png");
+
+        let saved_content =
+            serialize_config(&config, ConfigFormat::Toml, Some(&previous_content)).unwrap();
+        assert!(saved_content
+            .contains("# set by the last fetch\nfile_type = \"png\" # jpg or png"));
+        assert!(!saved_content.contains("source_kind"));
+        assert_eq!(
+            deserialize_config(&saved_content, ConfigFormat::Toml)
+                .unwrap()
+                .file_type,
+            "png"
+        );
+    }
+}
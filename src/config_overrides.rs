@@ -0,0 +1,347 @@
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::{app_config::AppConfig, app_constants::AppConstants};
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum OverrideOrigin {
+    Environment(String),
+    CommandLine,
+}
+
+impl fmt::Display for OverrideOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OverrideOrigin::Environment(name) => write!(f, "environment variable {}", name),
+            OverrideOrigin::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: String,
+    pub origin: OverrideOrigin,
+}
+
+// Layered on top of the config file: defaults < config file < environment < command line.
+// Keys are config field names, nested fields are addressed with "." (or "__" in
+// environment variable names), e.g. PICS2WALL_INTERVAL=300 or --set interval=300.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigOverrides {
+    overrides: Vec<ConfigOverride>,
+}
+
+impl ConfigOverrides {
+    pub const ENVIRONMENT_VARIABLE_PREFIX: &str = "PICS2WALL_";
+
+    // Friendlier names for the most commonly overridden fields
+    const KEY_ALIASES: [(&str, &str); 4] = [
+        ("source_url", "image_address"),
+        ("source_kind", "kind"),
+        ("interval", "auto_update_interval"),
+        ("fit_style", "wallpaper_fit_style"),
+    ];
+
+    pub fn load(args: &[String]) -> Self {
+        ConfigOverrides::from_sources(std::env::vars(), args)
+    }
+
+    fn from_sources(
+        environment_variables: impl Iterator<Item = (String, String)>,
+        args: &[String],
+    ) -> Self {
+        let mut config_overrides = ConfigOverrides::default();
+        let known_config = serde_json::to_value(AppConfig::default()).unwrap();
+
+        let mut environment_variables: Vec<(String, String)> = environment_variables
+            .filter(|(name, _)| {
+                name.starts_with(ConfigOverrides::ENVIRONMENT_VARIABLE_PREFIX)
+                    && name != AppConstants::SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE
+            })
+            .collect();
+        environment_variables.sort();
+
+        for (name, value) in environment_variables {
+            let key = ConfigOverrides::normalize_key(
+                &name[ConfigOverrides::ENVIRONMENT_VARIABLE_PREFIX.len()..].replace("__", "."),
+            );
+
+            if lookup_path(&known_config, &key).is_none() {
+                log::warn!("Environment variable {} does not match any config key, ignored", name);
+                continue;
+            }
+
+            config_overrides.overrides.push(ConfigOverride {
+                key,
+                value,
+                origin: OverrideOrigin::Environment(name),
+            });
+        }
+
+        let mut args_iter = args.iter();
+        while let Some(arg) = args_iter.next() {
+            let assignment = if arg == "--set" {
+                args_iter.next().cloned()
+            } else {
+                arg.strip_prefix("--set=").map(String::from)
+            };
+
+            let assignment = match assignment {
+                Some(assignment) => assignment,
+                None => continue,
+            };
+
+            match assignment.split_once('=') {
+                Some((key, value)) => {
+                    let key = ConfigOverrides::normalize_key(key);
+
+                    if lookup_path(&known_config, &key).is_none() {
+                        log::error!("Command line override {} does not match any config key, ignored", assignment);
+                        continue;
+                    }
+
+                    config_overrides.overrides.push(ConfigOverride {
+                        key,
+                        value: value.to_string(),
+                        origin: OverrideOrigin::CommandLine,
+                    });
+                }
+                None => log::error!("Command line override {} is not in key=value form, ignored", assignment),
+            }
+        }
+
+        for config_override in &config_overrides.overrides {
+            log::info!(
+                "Config key {} overridden from {}",
+                config_override.key,
+                config_override.origin
+            );
+        }
+
+        config_overrides
+    }
+
+    fn normalize_key(key: &str) -> String {
+        let key = key.trim().to_ascii_lowercase();
+
+        match ConfigOverrides::KEY_ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == key)
+        {
+            Some((_, field_name)) => field_name.to_string(),
+            None => key,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+
+    // Later overrides win, so the command line takes precedence over the environment
+    pub fn origin_of(&self, key: &str) -> Option<&OverrideOrigin> {
+        self.overrides
+            .iter()
+            .rev()
+            .find(|config_override| config_override.key == key)
+            .map(|config_override| &config_override.origin)
+    }
+
+    pub fn apply(&self, config: &AppConfig) -> Result<AppConfig, String> {
+        if self.is_empty() {
+            return Ok(config.clone());
+        }
+
+        let mut config_value = serde_json::to_value(config).map_err(|e| e.to_string())?;
+
+        for config_override in &self.overrides {
+            let target = lookup_path_mut(&mut config_value, &config_override.key)
+                .ok_or_else(|| format!("Unknown config key {}", config_override.key))?;
+
+            // string fields take the raw text, anything else is read as JSON (numbers, booleans)
+            *target = if target.is_string() {
+                Value::String(config_override.value.clone())
+            } else {
+                serde_json::from_str(&config_override.value)
+                    .unwrap_or_else(|_| Value::String(config_override.value.clone()))
+            };
+        }
+
+        serde_json::from_value(config_value)
+            .map_err(|e| format!("Config overrides could not be applied ({})", e))
+    }
+
+    // Puts the values of the overridden keys back to what the config file holds, so that
+    // saving from the GUI never persists environment or command line values
+    pub fn revert(&self, config: &AppConfig, file_config: &AppConfig) -> AppConfig {
+        if self.is_empty() {
+            return config.clone();
+        }
+
+        let mut config_value = serde_json::to_value(config).unwrap();
+        let file_config_value = serde_json::to_value(file_config).unwrap();
+
+        for config_override in &self.overrides {
+            if let (Some(target), Some(file_value)) = (
+                lookup_path_mut(&mut config_value, &config_override.key),
+                lookup_path(&file_config_value, &config_override.key),
+            ) {
+                *target = file_value.clone();
+            }
+        }
+
+        serde_json::from_value(config_value).unwrap_or_else(|_| config.clone())
+    }
+}
+
+fn lookup_path<'a>(value: &'a Value, key: &str) -> Option<&'a Value> {
+    key.split('.')
+        .try_fold(value, |current, segment| current.get(segment))
+}
+
+fn lookup_path_mut<'a>(value: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    key.split('.')
+        .try_fold(value, |current, segment| current.get_mut(segment))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ConfigOverride, ConfigOverrides, OverrideOrigin};
+    use crate::{
+        app_config::AppConfig, app_constants::AppConstants, app_gui::Enum,
+        source_auth::AuthMethod,
+    };
+
+    fn config_overrides(environment_variables: &[(&str, &str)], args: &[&str]) -> ConfigOverrides {
+        ConfigOverrides::from_sources(
+            environment_variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string())),
+            &args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>(),
+        )
+    }
+
+    fn file_config() -> AppConfig {
+        let file_config = AppConfig::default();
+        *file_config.image_address.lock().unwrap() = String::from("https://example.com/file.jpg");
+        *file_config.auto_update_interval.lock().unwrap() = Duration::from_secs(600);
+        file_config
+    }
+
+    #[test]
+    fn command_line_wins_over_environment_over_file() {
+        let file_config = file_config();
+
+        let environment_overrides =
+            config_overrides(&[("PICS2WALL_SOURCE_URL", "https://example.com/env.jpg")], &[]);
+        let config = environment_overrides.apply(&file_config).unwrap();
+        assert_eq!(*config.image_address.lock().unwrap(), "https://example.com/env.jpg");
+
+        let all_overrides = config_overrides(
+            &[("PICS2WALL_SOURCE_URL", "https://example.com/env.jpg")],
+            &["--set", "source_url=https://example.com/cli.jpg"],
+        );
+        let config = all_overrides.apply(&file_config).unwrap();
+        assert_eq!(*config.image_address.lock().unwrap(), "https://example.com/cli.jpg");
+        assert_eq!(all_overrides.origin_of("image_address"), Some(&OverrideOrigin::CommandLine));
+
+        // keys without an override keep the value of the file
+        assert_eq!(*config.auto_update_interval.lock().unwrap(), Duration::from_secs(600));
+        assert_eq!(all_overrides.origin_of("auto_update_interval"), None);
+    }
+
+    #[test]
+    fn applies_nested_keys_and_coerces_types() {
+        let config_overrides = config_overrides(
+            &[
+                ("PICS2WALL_SOURCE_AUTH__METHOD", "Bearer"),
+                ("PICS2WALL_INTERVAL", "300"),
+            ],
+            &[
+                "--set=download_limits.max_download_size=1024",
+                "--set",
+                "network.use_environment_proxy=false",
+                "--set",
+                "FIT_STYLE=Stretch",
+                "--set",
+                "caption_template=42",
+            ],
+        );
+        assert_eq!(
+            config_overrides.origin_of("source_auth.method"),
+            Some(&OverrideOrigin::Environment(String::from("PICS2WALL_SOURCE_AUTH__METHOD")))
+        );
+
+        let config = config_overrides.apply(&file_config()).unwrap();
+        assert_eq!(config.source_auth.lock().unwrap().method, AuthMethod::Bearer);
+        assert_eq!(*config.auto_update_interval.lock().unwrap(), Duration::from_secs(300));
+        assert_eq!(config.download_limits.lock().unwrap().max_download_size, 1024);
+        assert!(!config.network.lock().unwrap().use_environment_proxy);
+        assert_eq!(*config.wallpaper_fit_style.lock().unwrap(), Enum::Stretch);
+        // string fields take the text as it is, also when it looks like a number
+        assert_eq!(*config.caption_template.lock().unwrap(), "42");
+    }
+
+    #[test]
+    fn rejects_values_of_the_wrong_type() {
+        for assignment in [
+            "download_limits.max_download_size=lots",
+            "interval=soon",
+            "fit_style=Tiled",
+            "is_auto_update_active=maybe",
+        ] {
+            let config_overrides = config_overrides(&[], &["--set", assignment]);
+            assert!(!config_overrides.is_empty());
+            assert!(
+                config_overrides.apply(&file_config()).is_err(),
+                "{} was applied",
+                assignment
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_unknown_keys_and_malformed_assignments() {
+        let config_overrides = config_overrides(
+            &[
+                ("PICS2WALL_NO_SUCH_KEY", "1"),
+                ("PICS2WALL_NETWORK__NO_SUCH_KEY", "1"),
+                (AppConstants::SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE, "passphrase"),
+                ("SOURCE_URL", "https://example.com/unprefixed.jpg"),
+            ],
+            &["--set", "no_such_key=1", "--set", "image_address", "--set"],
+        );
+
+        assert!(config_overrides.is_empty());
+    }
+
+    #[test]
+    fn apply_rejects_unknown_keys() {
+        let config_overrides = ConfigOverrides {
+            overrides: vec![ConfigOverride {
+                key: String::from("no_such_key"),
+                value: String::from("1"),
+                origin: OverrideOrigin::CommandLine,
+            }],
+        };
+
+        assert!(config_overrides.apply(&file_config()).is_err());
+    }
+
+    #[test]
+    fn revert_restores_the_file_values() {
+        let file_config = file_config();
+        let config_overrides = config_overrides(&[], &["--set", "interval=120"]);
+        let config = config_overrides.apply(&file_config).unwrap();
+        *config.caption_template.lock().unwrap() = String::from("{title}");
+
+        let config_to_save = config_overrides.revert(&config, &file_config);
+        assert_eq!(*config_to_save.auto_update_interval.lock().unwrap(), Duration::from_secs(600));
+        // changes made in the GUI are kept
+        assert_eq!(*config_to_save.caption_template.lock().unwrap(), "{title}");
+    }
+}
@@ -0,0 +1,536 @@
+use std::{fmt, path::Path, time::Duration};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    download_limits::DownloadLimits,
+    http_server::HttpServerConfig,
+    duration_utils::format_duration,
+    image_source::{find_image_source, image_source_kinds},
+    image_validation::ImageRequirements,
+    log_utils::{LogRotation, LoggingConfig},
+    network_config::NetworkConfig,
+    source_auth::{AuthMethod, SourceAuth},
+    url_template::{validate_caption_template, validate_url_template},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ValidationSeverity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct ValidationIssue {
+    pub field: &'static str,
+    pub severity: ValidationSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            ValidationSeverity::Error => "error",
+            ValidationSeverity::Warning => "warning",
+        };
+        write!(f, "{} [{}]: {}", severity, self.field, self.message)
+    }
+}
+
+pub fn validate_config(config: &AppConfig) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+
+    let kind = config.kind.lock().unwrap().clone();
+    match find_image_source(&kind) {
+        Some(image_source) => image_source.validate(config, &mut issues),
+        None => issues.push(error(
+            "kind",
+            &format!(
+                "Unknown source kind \"{}\", supported: {}",
+                kind,
+                image_source_kinds().join(", ")
+            ),
+        )),
+    }
+    if let Err(e) = validate_url_template(&config.image_address.lock().unwrap()) {
+        issues.push(error("image_address", &e));
+    }
+    if let Err(e) = validate_caption_template(&config.caption_template.lock().unwrap()) {
+        issues.push(error("caption_template", &e));
+    }
+    validate_auto_update_interval(*config.auto_update_interval.lock().unwrap(), &mut issues);
+    if let Some(auto_update_cron) = &config.auto_update_cron {
+        issues.push(error(
+            "auto_update_cron",
+            &format!(
+                "Cron schedules (\"{}\") are not supported, use auto_update_interval instead",
+                auto_update_cron
+            ),
+        ));
+    }
+    validate_source_auth(&config.source_auth.lock().unwrap(), &mut issues);
+    validate_network_config(&config.network.lock().unwrap(), &mut issues);
+    validate_download_limits(&config.download_limits.lock().unwrap(), &mut issues);
+    validate_image_requirements(&config.image_requirements.lock().unwrap(), &mut issues);
+    validate_http_server_config(&config.http_server.lock().unwrap(), &mut issues);
+    validate_logging_config(&config.logging.lock().unwrap(), &mut issues);
+    validate_directory(
+        "data directory",
+        AppConstants::WALLPAPER_IMAGE_FILE_LOCATION,
+        &mut issues,
+    );
+    validate_directory("log directory", AppConstants::LOG_FILE_LOCATION, &mut issues);
+
+    issues
+}
+
+pub fn has_errors(issues: &[ValidationIssue]) -> bool {
+    issues
+        .iter()
+        .any(|issue| issue.severity == ValidationSeverity::Error)
+}
+
+pub fn issues_for_field<'a>(
+    issues: &'a [ValidationIssue],
+    field: &'a str,
+) -> impl Iterator<Item = &'a ValidationIssue> {
+    issues.iter().filter(move |issue| issue.field == field)
+}
+
+// Addresses of URL based sources, only direct image addresses need an image suffix
+pub fn validate_url_address(
+    image_address: &str,
+    is_image_expected: bool,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let field = "image_address";
+
+    let (scheme, rest) = match image_address.split_once("://") {
+        Some(split_address) => split_address,
+        None => {
+            issues.push(error(field, "The image address must be a URL, e.g. https://example.com/image.jpg"));
+            return;
+        }
+    };
+
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        issues.push(error(
+            field,
+            &format!("Unsupported URL scheme \"{}\", only http and https are supported", scheme),
+        ));
+    }
+
+    if rest.split('/').next().unwrap_or("").is_empty() {
+        issues.push(error(field, "The image address is missing a host name"));
+    }
+
+    if !is_image_expected {
+        return;
+    }
+
+    let path = image_address.split(['?', '#']).next().unwrap_or("").to_ascii_lowercase();
+    if !AppConstants::SUPPORTED_IMAGE_SUFFIXES
+        .iter()
+        .any(|suffix| path.ends_with(suffix))
+    {
+        issues.push(error(
+            field,
+            &format!(
+                "The image address must point directly to an image, supported suffixes: {}",
+                AppConstants::SUPPORTED_IMAGE_SUFFIXES.join(", ")
+            ),
+        ));
+    }
+}
+
+fn validate_auto_update_interval(auto_update_interval: Duration, issues: &mut Vec<ValidationIssue>) {
+    if auto_update_interval < AppConstants::AUTO_UPDATE_MIN_INTERVAL {
+        issues.push(error(
+            "auto_update_interval",
+            &format!(
+                "The auto-update interval is too short, the minimum is {}",
+                format_duration(AppConstants::AUTO_UPDATE_MIN_INTERVAL)
+            ),
+        ));
+    }
+}
+
+fn validate_source_auth(source_auth: &SourceAuth, issues: &mut Vec<ValidationIssue>) {
+    match source_auth.method {
+        AuthMethod::Basic | AuthMethod::Digest if source_auth.username.is_empty() => {
+            issues.push(error(
+                "source_auth.username",
+                "A username is required for Basic and Digest authentication",
+            ));
+        }
+        AuthMethod::Bearer if source_auth.token.is_empty() => {
+            issues.push(error(
+                "source_auth.token",
+                "A token is required for Bearer authentication",
+            ));
+        }
+        _ => {}
+    }
+
+    for name in source_auth.custom_headers.keys() {
+        if name.is_empty()
+            || name
+                .chars()
+                .any(|character| character == ':' || character.is_whitespace() || character.is_control())
+        {
+            issues.push(error(
+                "source_auth.custom_headers",
+                &format!("Invalid custom header name \"{}\"", name),
+            ));
+        }
+    }
+
+    if source_auth.has_secret_references()
+        && !Path::new(AppConstants::SECRETS_FILE_LOCATION).exists()
+    {
+        issues.push(warning(
+            "source_auth",
+            &format!(
+                "Secret references are used but {} does not exist, add secrets with \"pics2wall secrets add <name>\"",
+                AppConstants::SECRETS_FILE_LOCATION
+            ),
+        ));
+    }
+
+    if source_auth
+        .custom_headers
+        .values()
+        .any(|value| value.contains('\r') || value.contains('\n'))
+    {
+        issues.push(error(
+            "source_auth.custom_headers",
+            "Custom header values must be single-line",
+        ));
+    }
+}
+
+fn validate_network_config(network_config: &NetworkConfig, issues: &mut Vec<ValidationIssue>) {
+    if !network_config.proxy.is_empty() {
+        let proxy_scheme = network_config
+            .proxy
+            .split_once("://")
+            .map(|(scheme, _)| scheme.to_ascii_lowercase());
+
+        match proxy_scheme.as_deref() {
+            Some("http") | Some("https") | Some("socks4") | Some("socks4a") | Some("socks5")
+            | Some("socks5h") => {}
+            _ => issues.push(error(
+                "network.proxy",
+                "The proxy must be a URL with an http, https, socks4, socks4a, socks5 or socks5h scheme",
+            )),
+        }
+    }
+
+    for (field, location) in [
+        ("network.ca_bundle", &network_config.ca_bundle),
+        ("network.client_certificate", &network_config.client_certificate),
+        ("network.client_key", &network_config.client_key),
+    ] {
+        if !location.is_empty() && !Path::new(location).is_file() {
+            issues.push(error(field, &format!("File {} does not exist", location)));
+        }
+    }
+
+    if !network_config.client_key.is_empty() && network_config.client_certificate.is_empty() {
+        issues.push(error(
+            "network.client_certificate",
+            "A client key is configured without a client certificate",
+        ));
+    }
+
+    if network_config.user_agent.contains(['\r', '\n']) {
+        issues.push(error("network.user_agent", "The user agent must be single-line"));
+    }
+}
+
+fn validate_download_limits(download_limits: &DownloadLimits, issues: &mut Vec<ValidationIssue>) {
+    if download_limits.max_download_size == 0 {
+        issues.push(error(
+            "download_limits.max_download_size",
+            "The maximum download size must be greater than 0",
+        ));
+    }
+
+    if download_limits.max_image_width == 0 || download_limits.max_image_height == 0 {
+        issues.push(error(
+            "download_limits",
+            "The maximum image width and height must be greater than 0",
+        ));
+    }
+
+    if !download_limits.require_image_content_type {
+        issues.push(warning(
+            "download_limits.require_image_content_type",
+            "Responses are accepted without an image content type",
+        ));
+    }
+}
+
+fn validate_image_requirements(
+    image_requirements: &ImageRequirements,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    if image_requirements.min_aspect_ratio < 0.0 || image_requirements.max_aspect_ratio < 0.0 {
+        issues.push(error(
+            "image_requirements",
+            "Aspect ratios must not be negative, use 0 to disable the check",
+        ));
+    }
+
+    if image_requirements.min_aspect_ratio > 0.0
+        && image_requirements.max_aspect_ratio > 0.0
+        && image_requirements.min_aspect_ratio > image_requirements.max_aspect_ratio
+    {
+        issues.push(error(
+            "image_requirements",
+            "The minimum aspect ratio must not be greater than the maximum aspect ratio",
+        ));
+    }
+}
+
+fn validate_http_server_config(
+    http_server_config: &HttpServerConfig,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    if !http_server_config.enabled {
+        return;
+    }
+
+    if http_server_config.token.trim().is_empty() {
+        issues.push(error(
+            "http_server.token",
+            "A token is required when the HTTP server is enabled",
+        ));
+    }
+
+    if http_server_config.port == 0 {
+        issues.push(error("http_server.port", "The port must be greater than 0"));
+    }
+}
+
+fn validate_logging_config(logging_config: &LoggingConfig, issues: &mut Vec<ValidationIssue>) {
+    if logging_config.rotation == LogRotation::Size && logging_config.max_file_size_mb == 0 {
+        issues.push(error(
+            "logging.max_file_size_mb",
+            "The maximum log file size must be at least 1 MB",
+        ));
+    }
+
+    if logging_config.retention_count == 0 {
+        issues.push(error(
+            "logging.retention_count",
+            "At least one rolled log file must be kept",
+        ));
+    }
+
+    // log4rs only accepts module paths, e.g. pics2wall::download_utils
+    for module in logging_config.module_levels.keys() {
+        let is_module_path = module
+            .split("::")
+            .all(|segment| !segment.is_empty() && !segment.contains(':'));
+
+        if !is_module_path {
+            issues.push(warning(
+                "logging.module_levels",
+                &format!("{} is not a module path, its level is ignored", module),
+            ));
+        }
+    }
+}
+
+// Missing directories are created on start, so only an existing non-directory or a read-only
+// directory is reported
+fn validate_directory(field: &'static str, location: &str, issues: &mut Vec<ValidationIssue>) {
+    match std::fs::metadata(Path::new(location)) {
+        Ok(metadata) if !metadata.is_dir() => {
+            issues.push(warning(field, &format!("{} exists but is not a directory", location)));
+        }
+        Ok(metadata) if metadata.permissions().readonly() => {
+            issues.push(warning(field, &format!("{} is not writable", location)));
+        }
+        _ => {}
+    }
+}
+
+pub fn error(field: &'static str, message: &str) -> ValidationIssue {
+    ValidationIssue {
+        field,
+        severity: ValidationSeverity::Error,
+        message: message.to_string(),
+    }
+}
+
+pub fn warning(field: &'static str, message: &str) -> ValidationIssue {
+    ValidationIssue {
+        field,
+        severity: ValidationSeverity::Warning,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        error, has_errors, issues_for_field, validate_config, warning, ValidationIssue,
+        ValidationSeverity,
+    };
+    use crate::{app_config::AppConfig, app_constants::AppConstants, source_auth::AuthMethod};
+
+    fn error_fields(issues: &[ValidationIssue]) -> Vec<&'static str> {
+        issues
+            .iter()
+            .filter(|issue| issue.severity == ValidationSeverity::Error)
+            .map(|issue| issue.field)
+            .collect()
+    }
+
+    #[test]
+    fn default_config_is_valid() {
+        let issues = validate_config(&AppConfig::default());
+
+        assert!(!has_errors(&issues), "{:?}", issues);
+    }
+
+    #[test]
+    fn rejects_zero_and_too_short_intervals() {
+        let config = AppConfig::default();
+
+        for auto_update_interval in [
+            Duration::ZERO,
+            Duration::from_secs(1),
+            AppConstants::AUTO_UPDATE_MIN_INTERVAL - Duration::from_secs(1),
+        ] {
+            *config.auto_update_interval.lock().unwrap() = auto_update_interval;
+            assert_eq!(error_fields(&validate_config(&config)), vec!["auto_update_interval"]);
+        }
+
+        for auto_update_interval in [
+            AppConstants::AUTO_UPDATE_MIN_INTERVAL,
+            Duration::from_secs(7 * 24 * 60 * 60),
+        ] {
+            *config.auto_update_interval.lock().unwrap() = auto_update_interval;
+            assert!(error_fields(&validate_config(&config)).is_empty());
+        }
+    }
+
+    #[test]
+    fn rejects_cron_schedules() {
+        let mut config = AppConfig::default();
+        config.auto_update_cron = Some(String::from("0 */6 * * *"));
+
+        let issues = validate_config(&config);
+
+        assert_eq!(error_fields(&issues), vec!["auto_update_cron"]);
+        assert!(issues_for_field(&issues, "auto_update_cron")
+            .all(|issue| issue.message.contains("0 */6 * * *")));
+    }
+
+    #[test]
+    fn rejects_unknown_source_kinds() {
+        let config = AppConfig::default();
+        *config.kind.lock().unwrap() = String::from("Ftp");
+
+        assert_eq!(error_fields(&validate_config(&config)), vec!["kind"]);
+    }
+
+    #[test]
+    fn checks_url_addresses_of_http_sources() {
+        let config = AppConfig::default();
+
+        for image_address in [
+            "example.com/image.jpg",
+            "ftp://example.com/image.jpg",
+            "https:///image.jpg",
+            "https://example.com/page.html",
+            "https://example.com/{unknown}.jpg",
+        ] {
+            *config.image_address.lock().unwrap() = image_address.to_string();
+            assert!(
+                error_fields(&validate_config(&config)).contains(&"image_address"),
+                "{} was accepted",
+                image_address
+            );
+        }
+
+        *config.image_address.lock().unwrap() =
+            String::from("https://example.com/{date}/image.JPG?size=large");
+        assert!(error_fields(&validate_config(&config)).is_empty());
+    }
+
+    #[test]
+    fn checks_the_required_fields_of_command_sources() {
+        let config = AppConfig::default();
+        *config.kind.lock().unwrap() = String::from("Command");
+        *config.image_address.lock().unwrap() = String::from(" ");
+        config.command.lock().unwrap().timeout_seconds = 0;
+        config.command.lock().unwrap().working_directory = String::from("no/such/folder");
+
+        let mut fields = error_fields(&validate_config(&config));
+        fields.sort_unstable();
+
+        assert_eq!(
+            fields,
+            vec!["command.timeout_seconds", "command.working_directory", "image_address"]
+        );
+
+        *config.image_address.lock().unwrap() = String::from("render_chart.exe");
+        config.command.lock().unwrap().timeout_seconds = 30;
+        config.command.lock().unwrap().working_directory = String::new();
+
+        assert!(error_fields(&validate_config(&config)).is_empty());
+    }
+
+    #[test]
+    fn checks_auth_and_server_settings() {
+        let config = AppConfig::default();
+        config.source_auth.lock().unwrap().method = AuthMethod::Bearer;
+        config
+            .source_auth
+            .lock()
+            .unwrap()
+            .custom_headers
+            .insert(String::from("X-Api Key"), String::from("line\nbreak"));
+        config.http_server.lock().unwrap().enabled = true;
+        config.http_server.lock().unwrap().token = String::from("  ");
+
+        let fields = error_fields(&validate_config(&config));
+
+        assert!(fields.contains(&"source_auth.token"));
+        assert_eq!(
+            fields
+                .iter()
+                .filter(|field| **field == "source_auth.custom_headers")
+                .count(),
+            2
+        );
+        assert!(fields.contains(&"http_server.token"));
+    }
+
+    #[test]
+    fn only_errors_count_for_has_errors() {
+        assert!(!has_errors(&[]));
+        assert!(!has_errors(&[
+            warning("download_limits.require_image_content_type", "warning"),
+            warning("logging.module_levels", "warning"),
+        ]));
+        assert!(has_errors(&[
+            warning("logging.module_levels", "warning"),
+            error("auto_update_interval", "error"),
+        ]));
+
+        let config = AppConfig::default();
+        config.download_limits.lock().unwrap().require_image_content_type = false;
+        let issues = validate_config(&config);
+
+        assert!(issues_for_field(&issues, "download_limits.require_image_content_type")
+            .all(|issue| issue.severity == ValidationSeverity::Warning));
+        assert!(!has_errors(&issues));
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    thread,
+};
+
+use interprocess::local_socket::{LocalSocketListener, LocalSocketStream};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_gui::MyApp,
+    common_utils::check_source_switch,
+    config_format_utils::active_profile,
+    single_instance::HandoffRequest,
+    source_auth::mask_url_credentials,
+};
+
+// One JSON object per line in both directions, e.g. {"command":"fetch_now"}
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Status,
+    FetchNow,
+    StartAutoUpdate,
+    StopAutoUpdate,
+    SwitchSource { kind: String, image_address: String },
+    SwitchProfile { profile: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusReport>,
+}
+
+impl ControlResponse {
+    fn message(result: Result<String, String>) -> Self {
+        match result {
+            Ok(message) => ControlResponse {
+                ok: true,
+                message,
+                status: None,
+            },
+            Err(message) => ControlResponse {
+                ok: false,
+                message,
+                status: None,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusReport {
+    pub activity: String,
+    pub is_auto_update_active: bool,
+    pub kind: String,
+    pub image_address: String,
+    pub profile: String,
+    pub last_fetch_time: String,
+    pub last_fetch_result: String,
+    pub next_update_time: String,
+    pub image_title: String,
+    pub image_credit: String,
+}
+
+impl StatusReport {
+    pub fn of(my_app: &MyApp) -> Self {
+        let image_metadata = my_app.last_image_metadata.lock().unwrap().clone();
+
+        StatusReport {
+            activity: my_app.status.lock().unwrap().clone(),
+            is_auto_update_active: *my_app.config.is_auto_update_active.lock().unwrap(),
+            kind: my_app.config.kind.lock().unwrap().clone(),
+            image_address: mask_url_credentials(&my_app.config.image_address.lock().unwrap()),
+            profile: active_profile(),
+            last_fetch_time: my_app.last_fetch_time.lock().unwrap().clone(),
+            last_fetch_result: my_app.last_fetch_result.lock().unwrap().to_string(),
+            next_update_time: match *my_app.next_update_time.lock().unwrap() {
+                Some(next_update_time) => next_update_time.format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => String::from("N/A"),
+            },
+            image_title: image_metadata.title,
+            image_credit: image_metadata.credit,
+        }
+    }
+}
+
+// Named pipes are visible to every session on Windows, the user name keeps the instances of
+// different users apart
+pub fn control_socket_name() -> String {
+    format!(
+        "@pics2wall-control-{}",
+        std::env::var("USERNAME").unwrap_or_default()
+    )
+}
+
+// Serves the control API of the running instance, one thread per connected client
+pub fn start_control_server(my_app: MyApp) {
+    let listener = match LocalSocketListener::bind(control_socket_name()) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Control API could not be started - {}", e);
+            return;
+        }
+    };
+
+    log::info!("Control API listening on {}", control_socket_name());
+
+    thread::spawn(move || {
+        for connection in listener.incoming() {
+            match connection {
+                Ok(connection) => {
+                    let my_app = my_app.clone();
+                    thread::spawn(move || serve_control_connection(&my_app, connection));
+                }
+                Err(e) => log::error!("Control API connection failed - {}", e),
+            }
+        }
+    });
+}
+
+// Sends a single request to the running instance and waits for its response
+pub fn send_control_request(request: &ControlRequest) -> Result<ControlResponse, String> {
+    let connection = LocalSocketStream::connect(control_socket_name())
+        .map_err(|e| format!("pics2wall is not running ({})", e))?;
+    let mut connection = BufReader::new(connection);
+
+    let line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    writeln!(connection.get_mut(), "{}", line)
+        .map_err(|e| format!("Control request could not be sent - {}", e))?;
+
+    let mut response_line = String::new();
+    connection
+        .read_line(&mut response_line)
+        .map_err(|e| format!("Control response could not be read - {}", e))?;
+
+    serde_json::from_str(&response_line)
+        .map_err(|e| format!("Invalid control response - {}", e))
+}
+
+fn serve_control_connection(my_app: &MyApp, connection: LocalSocketStream) {
+    let mut connection = BufReader::new(connection);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        match connection.read_line(&mut line) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Control request could not be read - {}", e);
+                return;
+            }
+        }
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => handle_control_request(my_app, request),
+            Err(e) => ControlResponse::message(Err(format!("Invalid request - {}", e))),
+        };
+
+        let response_line = serde_json::to_string(&response).unwrap();
+        if let Err(e) = writeln!(connection.get_mut(), "{}", response_line) {
+            log::error!("Control response could not be sent - {}", e);
+            return;
+        }
+    }
+}
+
+pub fn handle_control_request(my_app: &MyApp, request: ControlRequest) -> ControlResponse {
+    log::info!("Control request {:?} received", request);
+
+    match request {
+        ControlRequest::Status => ControlResponse {
+            ok: true,
+            message: String::new(),
+            status: Some(StatusReport::of(my_app)),
+        },
+        ControlRequest::FetchNow => {
+            my_app.queued_requests.lock().unwrap().push(HandoffRequest::FetchNow);
+            ControlResponse::message(Ok(String::from(
+                "Fetch queued, it starts within one update tick",
+            )))
+        }
+        ControlRequest::StartAutoUpdate => ControlResponse::message(
+            my_app
+                .config
+                .validate()
+                .map(|_| {
+                    *my_app.config.is_auto_update_active.lock().unwrap() = true;
+                    String::from("Auto-update started")
+                })
+                .map_err(|e| format!("Auto-update cannot be started - {}", e)),
+        ),
+        ControlRequest::StopAutoUpdate => {
+            *my_app.config.is_auto_update_active.lock().unwrap() = false;
+            ControlResponse::message(Ok(String::from("Auto-update stopped")))
+        }
+        // config changes are applied by the scheduler between fetches, like the profile
+        // switches handed over by a second launch
+        ControlRequest::SwitchSource {
+            kind,
+            image_address,
+        } => ControlResponse::message(check_source_switch(my_app, &kind).map(|_| {
+            let message = format!(
+                "Switch to {} {} queued, it is applied within one update tick",
+                kind,
+                mask_url_credentials(&image_address)
+            );
+            my_app.queued_requests.lock().unwrap().push(HandoffRequest::SwitchSource {
+                kind,
+                image_address,
+            });
+            message
+        })),
+        ControlRequest::SwitchProfile { profile } => {
+            let message = format!(
+                "Switch to profile {} queued, it is applied within one update tick",
+                profile
+            );
+            my_app.queued_requests.lock().unwrap().push(HandoffRequest::SwitchProfile(profile));
+            ControlResponse::message(Ok(message))
+        }
+    }
+}
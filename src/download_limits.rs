@@ -0,0 +1,35 @@
+use image::io::Limits;
+use serde::{Deserialize, Serialize};
+
+// Guards against oversized downloads and decompression bombs
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadLimits {
+    pub max_download_size: u64,
+    pub require_image_content_type: bool,
+    pub max_image_width: u32,
+    pub max_image_height: u32,
+    pub max_decode_allocation: u64,
+}
+
+impl Default for DownloadLimits {
+    fn default() -> Self {
+        Self {
+            max_download_size: 20 * 1024 * 1024,
+            require_image_content_type: true,
+            max_image_width: 16384,
+            max_image_height: 16384,
+            max_decode_allocation: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl DownloadLimits {
+    pub fn image_limits(&self) -> Limits {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(self.max_image_width);
+        limits.max_image_height = Some(self.max_image_height);
+        limits.max_alloc = Some(self.max_decode_allocation);
+        limits
+    }
+}
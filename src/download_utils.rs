@@ -0,0 +1,370 @@
+use std::cell::RefCell;
+
+use chrono::{DateTime, Local};
+use curl::easy::Easy;
+use url::Url;
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    download_limits::DownloadLimits,
+    fetch_result::{CacheValidators, FetchError, ImageMetadata},
+    image_source::{FetchContext, FetchedImage},
+    network_config::NetworkConfig,
+    secrets_utils::{load_secrets, read_key_material},
+    source_auth::{mask_url_credentials, SourceAuth},
+};
+
+// The source credentials and network options with their secret references resolved
+pub struct FetchCredentials {
+    pub source_auth: SourceAuth,
+    pub network: NetworkConfig,
+}
+
+impl FetchCredentials {
+    // Decrypting the secrets file runs the key derivation, FetchContext keeps the result for
+    // the rest of the fetch
+    pub fn resolve(config: &AppConfig) -> Result<Self, String> {
+        let source_auth = config.source_auth.lock().unwrap().clone();
+        let network = config.network.lock().unwrap().clone();
+
+        if !source_auth.has_secret_references() && !network.has_secret_references() {
+            return Ok(FetchCredentials {
+                source_auth,
+                network,
+            });
+        }
+
+        let secrets_key_file = config.secrets_key_file.lock().unwrap().clone();
+        let secrets = load_secrets(&read_key_material(&secrets_key_file)?)?;
+
+        Ok(FetchCredentials {
+            source_auth: source_auth.resolve_secrets(&secrets)?,
+            network: network.resolve_secrets(&secrets)?,
+        })
+    }
+}
+
+struct DownloadResponse {
+    body: Vec<u8>,
+    status_code: u32,
+    etag: String,
+    last_modified: String,
+}
+
+// Downloads the URL with the configured authentication, network options and size limit.
+// Pages fetched to look up the image URL skip the image content checks.
+pub fn download(
+    context: &FetchContext,
+    url: &str,
+    is_image_expected: bool,
+) -> Result<Vec<u8>, FetchError> {
+    perform_download(context, url, is_image_expected, &[]).map(|response| response.body)
+}
+
+// Downloads the image, sending the validators of the previous download of the same URL.
+// Returns None when the server answers 304 Not Modified.
+pub fn download_image(
+    context: &FetchContext,
+    image_url: &str,
+) -> Result<Option<FetchedImage>, FetchError> {
+    let previous_validators = &context.previous_metadata.cache_validators;
+    let mut conditional_headers = Vec::new();
+
+    if previous_validators.url == image_url {
+        if !previous_validators.etag.is_empty() {
+            conditional_headers.push(format!("If-None-Match: {}", previous_validators.etag));
+        }
+        if !previous_validators.last_modified.is_empty() {
+            conditional_headers.push(format!(
+                "If-Modified-Since: {}",
+                previous_validators.last_modified
+            ));
+        }
+    }
+
+    let response = perform_download(context, image_url, true, &conditional_headers)?;
+
+    if response.status_code == 304 {
+        return Ok(None);
+    }
+
+    let capture_time = DateTime::parse_from_rfc2822(&response.last_modified)
+        .ok()
+        .map(|last_modified| last_modified.with_timezone(&Local));
+
+    Ok(Some(FetchedImage {
+        data: response.body,
+        metadata: ImageMetadata {
+            capture_time,
+            cache_validators: CacheValidators {
+                url: image_url.to_string(),
+                etag: response.etag,
+                last_modified: response.last_modified,
+            },
+            ..ImageMetadata::default()
+        },
+    }))
+}
+
+fn perform_download(
+    context: &FetchContext,
+    url: &str,
+    is_image_expected: bool,
+    extra_headers: &[String],
+) -> Result<DownloadResponse, FetchError> {
+    follow_redirects(url, |request_url| {
+        perform_request(context, request_url, is_image_expected, extra_headers)
+    })
+}
+
+fn perform_request(
+    context: &FetchContext,
+    url: &str,
+    is_image_expected: bool,
+    extra_headers: &[String],
+) -> Result<Hop<DownloadResponse>, FetchError> {
+    let (mut easy, download_limits) = new_fetch_handle(context, url, extra_headers)?;
+
+    let mut dst = Vec::new();
+    let mut is_download_too_large = false;
+    let response_headers = RefCell::new(Vec::new());
+    let transfer_result = {
+        let mut transfer = easy.transfer();
+        transfer
+            .header_function(|header| {
+                response_headers
+                    .borrow_mut()
+                    .push(String::from_utf8_lossy(header).trim().to_string());
+                true
+            })
+            .expect("Setting header function for transfer failed");
+        transfer
+            .write_function(|data| {
+                // returning fewer bytes than received aborts the transfer
+                if (dst.len() + data.len()) as u64 > download_limits.max_download_size {
+                    is_download_too_large = true;
+                    return Ok(0);
+                }
+                dst.extend_from_slice(data);
+                Ok(data.len())
+            })
+            .expect("Setting write function for transfer failed");
+
+        transfer.perform()
+    };
+
+    if transfer_result.is_ok() {
+        if let Some(redirect_url) = redirect_url(&mut easy) {
+            return Ok(Hop::Redirect(redirect_url));
+        }
+    }
+
+    check_download(
+        &mut easy,
+        transfer_result,
+        is_download_too_large,
+        is_image_expected,
+        &dst,
+        &download_limits,
+    )?;
+
+    let response_headers = response_headers.into_inner();
+
+    Ok(Hop::Response(DownloadResponse {
+        body: dst,
+        status_code: easy.response_code().unwrap_or(0),
+        etag: response_header_value(&response_headers, "etag"),
+        last_modified: response_header_value(&response_headers, "last-modified"),
+    }))
+}
+
+pub enum Hop<T> {
+    Response(T),
+    Redirect(String),
+}
+
+// Redirects are followed here rather than by libcurl, so that every hop gets a handle of its
+// own with the proxy and no-proxy list picked for the scheme and host of that hop
+pub fn follow_redirects<T>(
+    url: &str,
+    mut perform_request: impl FnMut(&str) -> Result<Hop<T>, FetchError>,
+) -> Result<T, FetchError> {
+    let mut request_url = url.to_string();
+
+    for _ in 0..=AppConstants::MAX_REDIRECTIONS {
+        match perform_request(&request_url)? {
+            Hop::Response(response) => return Ok(response),
+            Hop::Redirect(redirect_url) => {
+                log::info!("Redirected to {}", mask_url_credentials(&redirect_url));
+                request_url = redirect_url;
+            }
+        }
+    }
+
+    Err(FetchError::Transfer(format!(
+        "more than {} redirects",
+        AppConstants::MAX_REDIRECTIONS
+    )))
+}
+
+// The absolute target of a redirect response, libcurl resolves relative locations
+pub fn redirect_url(easy: &mut Easy) -> Option<String> {
+    match easy.response_code() {
+        Ok(301 | 302 | 303 | 307 | 308) => {
+            easy.redirect_url().ok().flatten().map(|redirect_url| redirect_url.to_string())
+        }
+        _ => None,
+    }
+}
+
+// Headers of interim responses (100 Continue) are reported too, only the ones after the last
+// status line belong to the final response
+fn response_header_value(headers: &[String], name: &str) -> String {
+    let final_response_start = headers
+        .iter()
+        .rposition(|header| header.starts_with("HTTP/"))
+        .unwrap_or(0);
+
+    headers[final_response_start..]
+        .iter()
+        .filter_map(|header| header.split_once(':'))
+        .find(|(header_name, _)| header_name.trim().eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.trim().to_string())
+        .unwrap_or_default()
+}
+
+// Fails without sending anything when the credentials cannot be resolved, an unauthenticated
+// request would only fail later with a less helpful status
+pub fn new_fetch_handle(
+    context: &FetchContext,
+    url: &str,
+    extra_headers: &[String],
+) -> Result<(Easy, DownloadLimits), FetchError> {
+    let credentials = context.credentials()?;
+    let mut easy = Easy::new();
+
+    let url_set_result = easy.url(url);
+    match url_set_result {
+        Ok(_) => {}
+        Err(_) => {
+            log::error!("Setting URL for the fetch handle failed");
+        }
+    }
+
+    // image URLs taken from a page, an API response or a feed and redirects can point to any
+    // host, the credentials and custom headers only go to the origin of the source address
+    let no_source_auth = SourceAuth::default();
+    let source_auth = if is_same_origin(url, &context.source_address) {
+        &credentials.source_auth
+    } else {
+        if credentials.source_auth != no_source_auth {
+            log::info!(
+                "Credentials not sent to {}, it is not the origin of the source address",
+                mask_url_credentials(url)
+            );
+        }
+        &no_source_auth
+    };
+
+    match easy
+        .unrestricted_auth(false)
+        .and_then(|_| source_auth.apply_to_handle(&mut easy, extra_headers))
+    {
+        Ok(_) => {}
+        Err(_) => log::error!("Setting authentication for the fetch handle failed"),
+    }
+
+    match credentials.network.apply_to_handle(&mut easy, url) {
+        Ok(_) => {}
+        Err(e) => log::error!("Setting network options for the fetch handle failed - {}", e),
+    }
+
+    // without these a server that accepts the connection but never sends holds the fetch forever
+    match easy
+        .connect_timeout(AppConstants::CONNECT_TIMEOUT)
+        .and_then(|_| easy.low_speed_limit(1))
+        .and_then(|_| easy.low_speed_time(AppConstants::STALLED_TRANSFER_TIMEOUT))
+    {
+        Ok(_) => {}
+        Err(_) => log::error!("Setting timeouts for the fetch handle failed"),
+    }
+
+    let download_limits = context.config.download_limits.lock().unwrap().clone();
+    match easy.max_filesize(download_limits.max_download_size) {
+        Ok(_) => {}
+        Err(_) => log::error!("Setting maximum download size failed"),
+    }
+
+    Ok((easy, download_limits))
+}
+
+// Same scheme, host and port
+fn is_same_origin(url: &str, source_address: &str) -> bool {
+    match (Url::parse(url), Url::parse(source_address)) {
+        (Ok(url), Ok(source_address)) => url.origin() == source_address.origin(),
+        _ => false,
+    }
+}
+
+fn check_download(
+    easy: &mut Easy,
+    transfer_result: Result<(), curl::Error>,
+    is_download_too_large: bool,
+    is_image_expected: bool,
+    body: &[u8],
+    download_limits: &DownloadLimits,
+) -> Result<(), FetchError> {
+    match transfer_result {
+        Err(e) if is_download_too_large || e.is_filesize_exceeded() => {
+            return Err(FetchError::TooLarge(download_limits.max_download_size));
+        }
+        Err(e) => return Err(FetchError::Transfer(e.to_string())),
+        Ok(_) => {}
+    }
+
+    match easy.response_code() {
+        Ok(status_code) if status_code >= 400 => return Err(FetchError::HttpStatus(status_code)),
+        Ok(304) => return Ok(()),
+        _ => {}
+    }
+
+    if !is_image_expected {
+        return Ok(());
+    }
+
+    // error and login pages are regularly served with a 200 status
+    if looks_like_html(body) {
+        return Err(FetchError::HtmlErrorPage);
+    }
+
+    let content_type = easy
+        .content_type()
+        .ok()
+        .flatten()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    if content_type.starts_with("text/html") {
+        return Err(FetchError::HtmlErrorPage);
+    }
+
+    if download_limits.require_image_content_type && !content_type.starts_with("image/") {
+        return Err(FetchError::UnexpectedContentType(if content_type.is_empty() {
+            String::from("(none)")
+        } else {
+            content_type
+        }));
+    }
+
+    Ok(())
+}
+
+fn looks_like_html(body: &[u8]) -> bool {
+    let start = String::from_utf8_lossy(&body[..body.len().min(512)])
+        .trim_start_matches('\u{feff}')
+        .trim_start()
+        .to_ascii_lowercase();
+
+    start.starts_with("<!doctype html") || start.starts_with("<html") || start.starts_with("<head")
+}
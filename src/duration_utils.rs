@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+const UNITS: [(char, u64); 4] = [('d', 86400), ('h', 3600), ('m', 60), ('s', 1)];
+
+// Accepts a plain number of seconds or unit-suffixed parts, e.g. 90s, 5m, 1h30m, 2d
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim().to_ascii_lowercase();
+
+    if input.is_empty() {
+        return Err(String::from("The duration is empty"));
+    }
+
+    if let Ok(seconds) = input.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let mut total_seconds: u64 = 0;
+    let mut number = String::new();
+
+    for character in input.chars().filter(|character| !character.is_whitespace()) {
+        if character.is_ascii_digit() {
+            number.push(character);
+            continue;
+        }
+
+        let unit_seconds = UNITS
+            .iter()
+            .find(|(unit, _)| *unit == character)
+            .map(|(_, unit_seconds)| *unit_seconds)
+            .ok_or_else(|| format!("Unknown time unit \"{}\", use d, h, m or s", character))?;
+
+        let value = number
+            .parse::<u64>()
+            .map_err(|_| format!("Missing number before \"{}\"", character))?;
+
+        total_seconds = value
+            .checked_mul(unit_seconds)
+            .and_then(|seconds| total_seconds.checked_add(seconds))
+            .ok_or_else(|| String::from("The duration is too long"))?;
+
+        number.clear();
+    }
+
+    if !number.is_empty() {
+        return Err(format!("Missing time unit after \"{}\"", number));
+    }
+
+    Ok(Duration::from_secs(total_seconds))
+}
+
+pub fn format_duration(duration: Duration) -> String {
+    let mut remaining_seconds = duration.as_secs();
+
+    if remaining_seconds == 0 {
+        return String::from("0s");
+    }
+
+    let mut formatted_duration = String::new();
+
+    for (unit, unit_seconds) in UNITS {
+        if remaining_seconds >= unit_seconds {
+            formatted_duration += &format!("{}{}", remaining_seconds / unit_seconds, unit);
+            remaining_seconds %= unit_seconds;
+        }
+    }
+
+    formatted_duration
+}
+
+// Serde support for shared duration config values, written as "1h30m" and read from either
+// such a string or a plain number of seconds (the format of older config files)
+pub mod shared_duration_serde {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use super::{format_duration, parse_duration};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum DurationValue {
+        Seconds(u64),
+        Text(String),
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Arc<Mutex<Duration>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_duration(*value.lock().unwrap()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Arc<Mutex<Duration>>, D::Error> {
+        let duration = match DurationValue::deserialize(deserializer)? {
+            DurationValue::Seconds(seconds) => Duration::from_secs(seconds),
+            DurationValue::Text(text) => parse_duration(&text).map_err(de::Error::custom)?,
+        };
+
+        Ok(Arc::new(Mutex::new(duration)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{format_duration, parse_duration};
+
+    #[test]
+    fn parses_plain_seconds() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration(" 0 "), Ok(Duration::from_secs(0)));
+    }
+
+    #[test]
+    fn parses_unit_suffixes() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(172_800)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1H 30M"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("1m1m"), Ok(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn rejects_malformed_input() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("   ").is_err());
+        assert!(parse_duration("5x").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert!(parse_duration("99999999999999999999s").is_err());
+        assert!(parse_duration("999999999999999999d").is_err());
+        assert!(parse_duration(&format!("{}s1s", u64::MAX)).is_err());
+    }
+
+    #[test]
+    fn formats_largest_units_first() {
+        assert_eq!(format_duration(Duration::from_secs(0)), "0s");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+        assert_eq!(format_duration(Duration::from_secs(90_061)), "1d1h1m1s");
+    }
+
+    #[test]
+    fn formatted_durations_parse_back() {
+        for seconds in [1, 59, 60, 3599, 86_400, 90_061, 1_000_000] {
+            let duration = Duration::from_secs(seconds);
+            assert_eq!(parse_duration(&format_duration(duration)), Ok(duration));
+        }
+    }
+}
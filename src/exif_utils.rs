@@ -0,0 +1,165 @@
+use std::io::Cursor;
+
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
+use exif::{Exif, In, Tag, Value};
+use image::DynamicImage;
+
+use crate::fetch_result::ImageMetadata;
+
+// The EXIF details of an image, orientation 1 is the upright default
+#[derive(Debug, PartialEq, Clone)]
+pub struct ExifDetails {
+    pub orientation: u32,
+    pub capture_time: Option<DateTime<Local>>,
+    pub camera: String,
+    pub gps_position: Option<(f64, f64)>,
+}
+
+impl Default for ExifDetails {
+    fn default() -> Self {
+        Self {
+            orientation: 1,
+            capture_time: None,
+            camera: String::from(""),
+            gps_position: None,
+        }
+    }
+}
+
+impl ExifDetails {
+    // The EXIF capture time is when the picture was taken and wins over the times reported by
+    // the source (e.g. Last-Modified of the download)
+    pub fn merge_into(&self, image_metadata: ImageMetadata) -> ImageMetadata {
+        ImageMetadata {
+            capture_time: self.capture_time.or(image_metadata.capture_time),
+            camera: self.camera.clone(),
+            gps_position: self.gps_position,
+            ..image_metadata
+        }
+    }
+}
+
+// Images without EXIF data (or with unreadable EXIF data) get the defaults
+pub fn read_exif_details(body: &[u8]) -> ExifDetails {
+    let exif = match exif::Reader::new().read_from_container(&mut Cursor::new(body)) {
+        Ok(exif) => exif,
+        Err(_) => return ExifDetails::default(),
+    };
+
+    ExifDetails {
+        orientation: exif
+            .get_field(Tag::Orientation, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .filter(|orientation| (1..=8).contains(orientation))
+            .unwrap_or(1),
+        capture_time: read_capture_time(&exif),
+        camera: [Tag::Make, Tag::Model]
+            .iter()
+            .filter_map(|tag| ascii_value(&exif, *tag))
+            .collect::<Vec<String>>()
+            .join(" "),
+        gps_position: read_gps_position(&exif),
+    }
+}
+
+// Rotates and flips the image upright, see the Orientation tag of the EXIF specification
+pub fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+fn ascii_value(exif: &Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| {
+                String::from_utf8_lossy(value)
+                    .trim_matches(char::from(0))
+                    .trim()
+                    .to_string()
+            })
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+// Without an offset tag the time is read as local time, cameras are usually set to it
+fn read_capture_time(exif: &Exif) -> Option<DateTime<Local>> {
+    let capture_time_field = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))?;
+
+    let mut capture_time = match &capture_time_field.value {
+        Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok()?,
+        _ => return None,
+    };
+
+    if let Some(Value::Ascii(values)) = exif
+        .get_field(Tag::OffsetTimeOriginal, In::PRIMARY)
+        .map(|field| &field.value)
+    {
+        if let Some(value) = values.first() {
+            let _ = capture_time.parse_offset(value);
+        }
+    }
+
+    let naive_capture_time = NaiveDate::from_ymd_opt(
+        capture_time.year.into(),
+        capture_time.month.into(),
+        capture_time.day.into(),
+    )?
+    .and_hms_opt(
+        capture_time.hour.into(),
+        capture_time.minute.into(),
+        capture_time.second.into(),
+    )?;
+
+    match capture_time.offset {
+        Some(offset) => FixedOffset::east_opt(i32::from(offset) * 60)?
+            .from_local_datetime(&naive_capture_time)
+            .single()
+            .map(|capture_time| capture_time.with_timezone(&Local)),
+        None => Local.from_local_datetime(&naive_capture_time).single(),
+    }
+}
+
+// Latitude and longitude in decimal degrees, south and west are negative
+fn read_gps_position(exif: &Exif) -> Option<(f64, f64)> {
+    let latitude = read_gps_coordinate(exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S")?;
+    let longitude = read_gps_coordinate(exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+
+    Some((latitude, longitude))
+}
+
+fn read_gps_coordinate(
+    exif: &Exif,
+    coordinate_tag: Tag,
+    reference_tag: Tag,
+    negative_reference: &str,
+) -> Option<f64> {
+    let degrees_minutes_seconds = match &exif.get_field(coordinate_tag, In::PRIMARY)?.value {
+        Value::Rational(values) if values.len() >= 3 => {
+            values[0].to_f64() + values[1].to_f64() / 60.0 + values[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    if !degrees_minutes_seconds.is_finite() {
+        return None;
+    }
+
+    match ascii_value(exif, reference_tag) {
+        Some(reference) if reference.eq_ignore_ascii_case(negative_reference) => {
+            Some(-degrees_minutes_seconds)
+        }
+        _ => Some(degrees_minutes_seconds),
+    }
+}
@@ -0,0 +1,288 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::Local;
+use egui::Ui;
+use feed_rs::model::Entry;
+use rand::seq::SliceRandom;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    app_gui::{ui_add_config_label, MyApp},
+    common_utils::write_file_atomically,
+    config_validation::{validate_url_address, ValidationIssue},
+    download_utils::{download, download_image},
+    fetch_result::{FetchError, ImageMetadata},
+    html_page_source::resolve_url,
+    image_source::{FetchContext, FetchedImage, ImageSource},
+    source_auth::mask_url_credentials,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum FeedSelection {
+    #[default]
+    Latest,
+    Random,
+    Sequential,
+}
+
+// Settings of RSS / Atom feeds. Random prefers entries not shown yet, Sequential walks the
+// entries from the oldest to the newest and starts over once all of them were shown.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct FeedSource {
+    pub selection: FeedSelection,
+}
+
+// Shown entries per configured feed address, kept in the data directory across restarts
+type SeenEntries = BTreeMap<String, Vec<String>>;
+
+// Enough to cover any realistic feed while keeping the state file small
+const MAX_SEEN_ENTRIES_PER_FEED: usize = 1000;
+
+struct FeedImage<'a> {
+    entry: &'a Entry,
+    image_url: String,
+}
+
+impl FeedSource {
+    pub fn select_image_url(
+        &self,
+        feed_content: &[u8],
+        feed_url: &str,
+        feed_address: &str,
+    ) -> Result<(String, ImageMetadata), String> {
+        let feed = feed_rs::parser::parse(feed_content)
+            .map_err(|e| format!("the feed cannot be parsed ({})", e))?;
+
+        let mut feed_images: Vec<FeedImage> = feed
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                find_entry_image_url(entry).map(|image_url| FeedImage { entry, image_url })
+            })
+            .collect();
+
+        if feed_images.is_empty() {
+            return Err(String::from("no feed entry contains an image"));
+        }
+
+        // newest first, entries without dates keep their feed order
+        feed_images.sort_by_key(|feed_image| {
+            std::cmp::Reverse(feed_image.entry.published.or(feed_image.entry.updated))
+        });
+
+        let seen_entry_ids = load_seen_entries()
+            .remove(feed_address)
+            .unwrap_or_default();
+        let unseen_images: Vec<&FeedImage> = feed_images
+            .iter()
+            .filter(|feed_image| !seen_entry_ids.contains(&feed_image.entry.id))
+            .collect();
+
+        let selected_image = match self.selection {
+            FeedSelection::Latest => &feed_images[0],
+            FeedSelection::Random => {
+                let candidates: Vec<&FeedImage> = if unseen_images.is_empty() {
+                    feed_images.iter().collect()
+                } else {
+                    unseen_images
+                };
+                candidates.choose(&mut rand::thread_rng()).copied().unwrap()
+            }
+            // the oldest entry not shown yet, then the least recently shown one
+            FeedSelection::Sequential => unseen_images.last().copied().unwrap_or_else(|| {
+                feed_images
+                    .iter()
+                    .min_by_key(|feed_image| {
+                        seen_entry_ids
+                            .iter()
+                            .position(|seen_entry_id| *seen_entry_id == feed_image.entry.id)
+                    })
+                    .unwrap()
+            }),
+        };
+
+        let image_metadata = ImageMetadata {
+            title: selected_image
+                .entry
+                .title
+                .as_ref()
+                .map(|title| title.content.clone())
+                .unwrap_or_default(),
+            credit: selected_image
+                .entry
+                .authors
+                .iter()
+                .map(|author| author.name.clone())
+                .collect::<Vec<String>>()
+                .join(", "),
+            capture_time: selected_image
+                .entry
+                .published
+                .or(selected_image.entry.updated)
+                .map(|entry_time| entry_time.with_timezone(&Local)),
+            entry_id: selected_image.entry.id.clone(),
+            ..ImageMetadata::default()
+        };
+
+        Ok((
+            resolve_url(feed_url, selected_image.image_url.trim())?,
+            image_metadata,
+        ))
+    }
+}
+
+// Picks an entry of the RSS / Atom feed at the image address
+pub struct Feed;
+
+impl ImageSource for Feed {
+    fn kind(&self) -> &'static str {
+        "Feed"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "Feed address:"
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        let feed_content = download(context, &context.source_address, false)?;
+        let feed_source = context.config.feed.lock().unwrap().clone();
+
+        let (image_url, entry_metadata) = feed_source
+            .select_image_url(
+                &feed_content,
+                &context.source_address,
+                &context.config.image_address.lock().unwrap(),
+            )
+            .map_err(FetchError::ImageNotFound)?;
+
+        log::info!(
+            "Image URL selected from feed entry {}: {}",
+            entry_metadata.entry_id,
+            mask_url_credentials(&image_url)
+        );
+
+        Ok(download_image(context, &image_url)?.map(|fetched_image| FetchedImage {
+            metadata: ImageMetadata {
+                cache_validators: fetched_image.metadata.cache_validators,
+                capture_time: entry_metadata.capture_time.or(fetched_image.metadata.capture_time),
+                ..entry_metadata
+            },
+            ..fetched_image
+        }))
+    }
+
+    fn on_image_applied(&self, context: &FetchContext, metadata: &ImageMetadata) {
+        mark_feed_entry_seen(&context.config.image_address.lock().unwrap(), &metadata.entry_id);
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        validate_url_address(&config.image_address.lock().unwrap(), false, issues);
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, _desired_width: f32) {
+        let feed_handle = &mut *my_app.config.feed.lock().unwrap();
+
+        let is_overridden = ui_add_config_label(ui, my_app, "Entry selection:", "feed.selection");
+
+        ui.add_enabled_ui(!is_overridden, |ui| {
+            egui::ComboBox::from_id_source("feed_selection")
+                .selected_text(format!("{:?}", feed_handle.selection))
+                .show_ui(ui, |ui| {
+                    for selection in [
+                        FeedSelection::Latest,
+                        FeedSelection::Random,
+                        FeedSelection::Sequential,
+                    ] {
+                        ui.selectable_value(
+                            &mut feed_handle.selection,
+                            selection,
+                            format!("{:?}", selection),
+                        );
+                    }
+                });
+        });
+
+        ui.end_row();
+    }
+}
+
+// Enclosures and media:content are both reported as media content, the first <img> of the
+// entry body is the fallback
+fn find_entry_image_url(entry: &Entry) -> Option<String> {
+    let media_image_url = entry
+        .media
+        .iter()
+        .flat_map(|media_object| media_object.content.iter())
+        .filter(|media_content| match &media_content.content_type {
+            Some(content_type) => content_type.to_string().starts_with("image/"),
+            None => true,
+        })
+        .find_map(|media_content| media_content.url.as_ref().map(|url| url.to_string()));
+
+    let enclosure_image_url = || {
+        entry
+            .links
+            .iter()
+            .filter(|link| link.rel.as_deref() == Some("enclosure"))
+            .find(|link| link.media_type.as_deref().unwrap_or("").starts_with("image/"))
+            .map(|link| link.href.clone())
+    };
+
+    let body_image_url = || {
+        entry
+            .content
+            .as_ref()
+            .and_then(|content| content.body.as_deref())
+            .into_iter()
+            .chain(entry.summary.as_ref().map(|summary| summary.content.as_str()))
+            .find_map(first_img_src)
+    };
+
+    media_image_url
+        .or_else(enclosure_image_url)
+        .or_else(body_image_url)
+}
+
+fn first_img_src(html: &str) -> Option<String> {
+    let selector = Selector::parse("img[src]").unwrap();
+
+    Html::parse_fragment(html)
+        .select(&selector)
+        .find_map(|element| element.value().attr("src"))
+        .map(|src| src.to_string())
+}
+
+fn load_seen_entries() -> SeenEntries {
+    std::fs::read(AppConstants::FEED_STATE_FILE_LOCATION)
+        .ok()
+        .and_then(|content| serde_json::from_slice(&content).ok())
+        .unwrap_or_default()
+}
+
+// Called once the entry's image was applied, the list is kept in the order entries were shown
+fn mark_feed_entry_seen(feed_address: &str, entry_id: &str) {
+    let mut seen_entries = load_seen_entries();
+    let seen_entry_ids = seen_entries.entry(feed_address.to_string()).or_default();
+
+    seen_entry_ids.retain(|seen_entry_id| seen_entry_id != entry_id);
+    seen_entry_ids.push(entry_id.to_string());
+
+    if seen_entry_ids.len() > MAX_SEEN_ENTRIES_PER_FEED {
+        let excess_entries = seen_entry_ids.len() - MAX_SEEN_ENTRIES_PER_FEED;
+        seen_entry_ids.drain(..excess_entries);
+    }
+
+    match serde_json::to_vec_pretty(&seen_entries) {
+        Ok(content) => {
+            match write_file_atomically(Path::new(AppConstants::FEED_STATE_FILE_LOCATION), &content) {
+                Ok(_) => {}
+                Err(e) => log::error!("Feed state could not be saved - {}", e),
+            }
+        }
+        Err(e) => log::error!("Feed state could not be serialized - {}", e),
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FetchError {
+    Transfer(String),
+    HttpStatus(u32),
+    TooLarge(u64),
+    UnexpectedContentType(String),
+    HtmlErrorPage,
+    InvalidImage(String),
+    ImageNotFound(String),
+    InvalidAddress(String),
+    FileAccess(String),
+    UnknownSourceKind(String),
+    CommandFailed(Option<i32>, String),
+    CommandTimedOut(u64),
+    Credentials(String),
+}
+
+impl FetchError {
+    // Stable name of the error kind, used as a metrics label
+    pub fn reason(&self) -> &'static str {
+        match self {
+            FetchError::Transfer(_) => "transfer",
+            FetchError::HttpStatus(_) => "http_status",
+            FetchError::TooLarge(_) => "too_large",
+            FetchError::UnexpectedContentType(_) => "unexpected_content_type",
+            FetchError::HtmlErrorPage => "html_error_page",
+            FetchError::InvalidImage(_) => "invalid_image",
+            FetchError::ImageNotFound(_) => "image_not_found",
+            FetchError::InvalidAddress(_) => "invalid_address",
+            FetchError::FileAccess(_) => "file_access",
+            FetchError::UnknownSourceKind(_) => "unknown_source_kind",
+            FetchError::CommandFailed(_, _) => "command_failed",
+            FetchError::CommandTimedOut(_) => "command_timed_out",
+            FetchError::Credentials(_) => "credentials",
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::Transfer(reason) => write!(f, "transfer failed ({})", reason),
+            FetchError::HttpStatus(status_code) => write!(f, "HTTP status {}", status_code),
+            FetchError::TooLarge(max_download_size) => {
+                write!(f, "download exceeds the {} byte limit", max_download_size)
+            }
+            FetchError::UnexpectedContentType(content_type) => {
+                write!(f, "unexpected content type {}", content_type)
+            }
+            FetchError::HtmlErrorPage => write!(f, "received an HTML page instead of an image"),
+            FetchError::InvalidImage(reason) => write!(f, "invalid image ({})", reason),
+            FetchError::ImageNotFound(reason) => write!(f, "no image found ({})", reason),
+            FetchError::InvalidAddress(reason) => write!(f, "invalid address ({})", reason),
+            FetchError::FileAccess(reason) => write!(f, "file access failed ({})", reason),
+            FetchError::UnknownSourceKind(kind) => write!(f, "unknown source kind {}", kind),
+            FetchError::CommandFailed(Some(exit_code), stderr) => {
+                write!(f, "command exited with code {} ({})", exit_code, stderr)
+            }
+            FetchError::CommandFailed(None, reason) => write!(f, "command failed ({})", reason),
+            FetchError::CommandTimedOut(timeout_seconds) => {
+                write!(f, "command did not finish within {} seconds", timeout_seconds)
+            }
+            FetchError::Credentials(reason) => {
+                write!(f, "credentials could not be resolved ({})", reason)
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum FetchResult {
+    NotAttempted,
+    Succeeded,
+    NotModified,
+    Failed(FetchError),
+}
+
+impl fmt::Display for FetchResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchResult::NotAttempted => write!(f, "N/A"),
+            FetchResult::Succeeded => write!(f, "Succeeded"),
+            FetchResult::NotModified => write!(f, "Not modified"),
+            FetchResult::Failed(fetch_error) => write!(f, "Failed - {}", fetch_error),
+        }
+    }
+}
+
+// Details sources provide along with the image, completed from the image's EXIF data.
+// entry_id identifies the feed entry or file the image was taken from, the GPS position is
+// latitude and longitude in decimal degrees.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImageMetadata {
+    pub title: String,
+    pub credit: String,
+    pub capture_time: Option<DateTime<Local>>,
+    pub entry_id: String,
+    pub camera: String,
+    pub gps_position: Option<(f64, f64)>,
+    pub cache_validators: CacheValidators,
+}
+
+// HTTP validators of the last downloaded image, sent back to skip unchanged downloads
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CacheValidators {
+    pub url: String,
+    pub etag: String,
+    pub last_modified: String,
+}
+
+// One fetch of the fetch history, the newest AppConstants::FETCH_HISTORY_LENGTH fetches are kept
+#[derive(Debug, Clone, Serialize)]
+pub struct FetchHistoryEntry {
+    pub time: String,
+    pub kind: String,
+    pub image_address: String,
+    pub result: String,
+    pub duration_ms: u64,
+}
+
+// Cache validators of the stored image and of the image applied as wallpaper. Conditional
+// requests only use the applied ones, so a stored image that was never applied (e.g. after a
+// test fetch) is downloaded again by the next update instead of being reported as unchanged.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct WallpaperValidators {
+    stored: Option<CacheValidators>,
+    applied: CacheValidators,
+}
+
+impl WallpaperValidators {
+    pub fn record_stored(&mut self, cache_validators: CacheValidators) {
+        self.stored = Some(cache_validators);
+    }
+
+    // Called once the stored image was set as the wallpaper
+    pub fn record_applied(&mut self) {
+        if let Some(cache_validators) = self.stored.take() {
+            self.applied = cache_validators;
+        }
+    }
+
+    pub fn applied(&self) -> &CacheValidators {
+        &self.applied
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validators(etag: &str) -> CacheValidators {
+        CacheValidators {
+            url: String::from("https://example.com/image.jpg"),
+            etag: String::from(etag),
+            last_modified: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_fetch_does_not_change_the_applied_validators() {
+        let mut wallpaper_validators = WallpaperValidators::default();
+
+        // The test fetch stores the image without applying it, so the following auto-update
+        // must not send its validators and skip the wallpaper
+        wallpaper_validators.record_stored(validators("\"a\""));
+        assert_eq!(wallpaper_validators.applied(), &CacheValidators::default());
+
+        // The auto-update downloads the same image again and applies it
+        wallpaper_validators.record_stored(validators("\"a\""));
+        wallpaper_validators.record_applied();
+        assert_eq!(wallpaper_validators.applied(), &validators("\"a\""));
+
+        // A later test fetch of a newer image keeps the validators of the wallpaper
+        wallpaper_validators.record_stored(validators("\"b\""));
+        assert_eq!(wallpaper_validators.applied(), &validators("\"a\""));
+    }
+
+    #[test]
+    fn applying_without_a_stored_image_keeps_the_validators() {
+        let mut wallpaper_validators = WallpaperValidators::default();
+        wallpaper_validators.record_stored(validators("\"a\""));
+        wallpaper_validators.record_applied();
+
+        // E.g. a not modified fetch followed by applying the current wallpaper again
+        wallpaper_validators.record_applied();
+        assert_eq!(wallpaper_validators.applied(), &validators("\"a\""));
+    }
+}
@@ -0,0 +1,205 @@
+use egui::Ui;
+use regex::Regex;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::{
+    app_config::AppConfig,
+    app_gui::{ui_add_config_label, MyApp},
+    config_validation::{error, validate_url_address, ValidationIssue},
+    download_utils::{download, download_image},
+    fetch_result::FetchError,
+    image_source::{FetchContext, FetchedImage, ImageSource},
+    source_auth::mask_url_credentials,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum HtmlExtraction {
+    #[default]
+    OgImage,
+    CssSelector,
+    Regex,
+}
+
+// Settings of web pages publishing an image under a changing URL. The image URL is taken from
+// the og:image meta tag, the first element matching css_selector (src, then href / content
+// attribute) or the first capture group of regex, and resolved relative to the page URL.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HtmlPageSource {
+    pub extraction: HtmlExtraction,
+    pub css_selector: String,
+    pub regex: String,
+}
+
+impl HtmlPageSource {
+    pub fn extract_image_url(&self, page: &str, page_url: &str) -> Result<String, String> {
+        let found_url = match self.extraction {
+            HtmlExtraction::OgImage => first_attribute(
+                page,
+                "meta[property=\"og:image\"], meta[property=\"og:image:url\"], meta[name=\"og:image\"]",
+                &["content"],
+            )?
+            .ok_or_else(|| String::from("no og:image meta tag"))?,
+            HtmlExtraction::CssSelector => {
+                first_attribute(page, &self.css_selector, &["src", "href", "content"])?
+                    .ok_or_else(|| format!("no element with an image URL matches {}", self.css_selector))?
+            }
+            HtmlExtraction::Regex => {
+                let regex = Regex::new(&self.regex).map_err(|e| e.to_string())?;
+                let captures = regex
+                    .captures(page)
+                    .ok_or_else(|| format!("no match for {}", self.regex))?;
+
+                // the first capture group when there is one, the whole match otherwise
+                captures
+                    .get(1)
+                    .or_else(|| captures.get(0))
+                    .map(|found| found.as_str().replace("&amp;", "&"))
+                    .unwrap_or_default()
+            }
+        };
+
+        resolve_url(page_url, found_url.trim())
+    }
+}
+
+// Looks up the image URL on the page at the image address
+pub struct HtmlPage;
+
+impl ImageSource for HtmlPage {
+    fn kind(&self) -> &'static str {
+        "HtmlPage"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "Page address:"
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        let page = download(context, &context.source_address, false)?;
+        let html_page_source = context.config.html_page.lock().unwrap().clone();
+
+        let image_url = html_page_source
+            .extract_image_url(&String::from_utf8_lossy(&page), &context.source_address)
+            .map_err(FetchError::ImageNotFound)?;
+
+        log::info!("Image URL found on the page: {}", mask_url_credentials(&image_url));
+
+        download_image(context, &image_url)
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        validate_url_address(&config.image_address.lock().unwrap(), false, issues);
+        validate_html_page_source(&config.html_page.lock().unwrap(), issues);
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, desired_width: f32) {
+        let html_page_handle = &mut *my_app.config.html_page.lock().unwrap();
+
+        let is_overridden =
+            ui_add_config_label(ui, my_app, "Image URL from:", "html_page.extraction");
+
+        ui.add_enabled_ui(!is_overridden, |ui| {
+            egui::ComboBox::from_id_source("html_page_extraction")
+                .selected_text(format!("{:?}", html_page_handle.extraction))
+                .show_ui(ui, |ui| {
+                    for extraction in [
+                        HtmlExtraction::OgImage,
+                        HtmlExtraction::CssSelector,
+                        HtmlExtraction::Regex,
+                    ] {
+                        ui.selectable_value(
+                            &mut html_page_handle.extraction,
+                            extraction,
+                            format!("{:?}", extraction),
+                        );
+                    }
+                });
+        });
+
+        ui.end_row();
+
+        match html_page_handle.extraction {
+            HtmlExtraction::CssSelector => {
+                let is_overridden =
+                    ui_add_config_label(ui, my_app, "CSS selector:", "html_page.css_selector");
+                ui.add_enabled(
+                    !is_overridden,
+                    egui::TextEdit::singleline(&mut html_page_handle.css_selector)
+                        .hint_text("e.g. div.webcam img")
+                        .desired_width(desired_width),
+                );
+
+                ui.end_row();
+            }
+            HtmlExtraction::Regex => {
+                let is_overridden = ui_add_config_label(ui, my_app, "Regex:", "html_page.regex");
+                ui.add_enabled(
+                    !is_overridden,
+                    egui::TextEdit::singleline(&mut html_page_handle.regex)
+                        .desired_width(desired_width),
+                )
+                .on_hover_text("The first capture group (or the whole match) is the image URL");
+
+                ui.end_row();
+            }
+            HtmlExtraction::OgImage => {}
+        }
+    }
+}
+
+fn validate_html_page_source(html_page_source: &HtmlPageSource, issues: &mut Vec<ValidationIssue>) {
+    match html_page_source.extraction {
+        HtmlExtraction::OgImage => {}
+        HtmlExtraction::CssSelector => {
+            if html_page_source.css_selector.is_empty() {
+                issues.push(error(
+                    "html_page.css_selector",
+                    "A CSS selector is required for the CssSelector extraction",
+                ));
+            } else if let Err(e) = parse_css_selector(&html_page_source.css_selector) {
+                issues.push(error("html_page.css_selector", &e));
+            }
+        }
+        HtmlExtraction::Regex => {
+            if html_page_source.regex.is_empty() {
+                issues.push(error("html_page.regex", "A regex is required for the Regex extraction"));
+            } else if let Err(e) = regex::Regex::new(&html_page_source.regex) {
+                issues.push(error("html_page.regex", &format!("The regex is invalid - {}", e)));
+            }
+        }
+    }
+}
+
+pub fn parse_css_selector(css_selector: &str) -> Result<Selector, String> {
+    Selector::parse(css_selector)
+        .map_err(|e| format!("\"{}\" is not a valid CSS selector ({:?})", css_selector, e.kind))
+}
+
+fn first_attribute(
+    page: &str,
+    css_selector: &str,
+    attribute_names: &[&str],
+) -> Result<Option<String>, String> {
+    let selector = parse_css_selector(css_selector)?;
+    let document = Html::parse_document(page);
+
+    let found_attribute = document.select(&selector).find_map(|element| {
+        attribute_names
+            .iter()
+            .filter_map(|attribute_name| element.value().attr(attribute_name))
+            .find(|value| !value.trim().is_empty())
+            .map(|value| value.to_string())
+    });
+
+    Ok(found_attribute)
+}
+
+pub fn resolve_url(base_url: &str, url: &str) -> Result<String, String> {
+    Url::parse(base_url)
+        .and_then(|base_url| base_url.join(url))
+        .map(|resolved_url| resolved_url.to_string())
+        .map_err(|e| format!("{} cannot be resolved against {} ({})", url, base_url, e))
+}
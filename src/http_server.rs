@@ -0,0 +1,204 @@
+use std::{fmt, io::Cursor, thread};
+
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    app_gui::MyApp,
+    common_utils::current_wallpaper_image_location,
+    control_api::{handle_control_request, ControlRequest, ControlResponse, StatusReport},
+    secrets_utils::{is_secret_reference, load_secrets, read_key_material, resolve_secret_reference},
+    source_auth::mask_secret,
+};
+
+// Opt-in status and control endpoint for browsers and monitoring scripts. It only listens on
+// localhost and every request needs the token, either as "Authorization: Bearer <token>" or as
+// the token query parameter. Changes take effect after a restart.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpServerConfig {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for HttpServerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8787,
+            token: String::from(""),
+        }
+    }
+}
+
+impl fmt::Debug for HttpServerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpServerConfig")
+            .field("enabled", &self.enabled)
+            .field("port", &self.port)
+            .field("token", &mask_secret(&self.token))
+            .finish()
+    }
+}
+
+impl HttpServerConfig {
+    fn resolve_token(&self, secrets_key_file: &str) -> Result<String, String> {
+        if !is_secret_reference(&self.token) {
+            return Ok(self.token.clone());
+        }
+
+        let secrets = load_secrets(&read_key_material(secrets_key_file)?)?;
+
+        resolve_secret_reference(&self.token, &secrets)
+    }
+}
+
+pub fn start_http_server(my_app: MyApp) {
+    let http_server_config = my_app.config.http_server.lock().unwrap().clone();
+
+    if !http_server_config.enabled {
+        return;
+    }
+
+    let secrets_key_file = my_app.config.secrets_key_file.lock().unwrap().clone();
+    let token = match http_server_config.resolve_token(&secrets_key_file) {
+        Ok(token) if !token.is_empty() => token,
+        Ok(_) => {
+            log::error!("HTTP server not started, it requires a token");
+            return;
+        }
+        Err(e) => {
+            log::error!("HTTP server not started, the token could not be resolved - {}", e);
+            return;
+        }
+    };
+
+    let address = format!("127.0.0.1:{}", http_server_config.port);
+    let server = match Server::http(&address) {
+        Ok(server) => server,
+        Err(e) => {
+            log::error!("HTTP server could not listen on {} - {}", address, e);
+            return;
+        }
+    };
+
+    log::info!("HTTP server listening on http://{}", address);
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            handle_http_request(&my_app, &token, request);
+        }
+    });
+}
+
+fn handle_http_request(my_app: &MyApp, token: &str, request: Request) {
+    let (path, query) = match request.url().split_once('?') {
+        Some((path, query)) => (path.to_string(), query.to_string()),
+        None => (request.url().to_string(), String::new()),
+    };
+
+    let response = if !is_authorized(&request, &query, token) {
+        log::warn!("HTTP request to {} rejected, missing or wrong token", path);
+        json_response(401, &error_response("Missing or wrong token"))
+    } else {
+        match (request.method(), path.as_str()) {
+            (Method::Get, "/status") => json_response(200, &StatusReport::of(my_app)),
+            (Method::Get, "/current.jpg") => current_image_response(),
+            (Method::Get, "/history") => {
+                json_response(200, &*my_app.fetch_history.lock().unwrap())
+            }
+            (Method::Get, "/metrics") => {
+                Response::from_string(my_app.metrics.lock().unwrap().to_exposition_format())
+                    .with_header(
+                        Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap(),
+                    )
+            }
+            (Method::Post, "/fetch-now") => control_response(my_app, ControlRequest::FetchNow),
+            (Method::Post, "/pause") => control_response(my_app, ControlRequest::StopAutoUpdate),
+            (Method::Post, "/resume") => {
+                control_response(my_app, ControlRequest::StartAutoUpdate)
+            }
+            (
+                _,
+                "/status" | "/current.jpg" | "/history" | "/metrics" | "/fetch-now" | "/pause"
+                | "/resume",
+            ) => json_response(405, &error_response("Method not allowed")),
+            _ => json_response(404, &error_response("Not found")),
+        }
+    };
+
+    if let Err(e) = request.respond(response) {
+        log::error!("HTTP response to {} could not be sent - {}", path, e);
+    }
+}
+
+fn is_authorized(request: &Request, query: &str, token: &str) -> bool {
+    let header_token = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .map(|header_token| header_token.trim().to_string());
+
+    let query_token = url::form_urlencoded::parse(query.as_bytes())
+        .find(|(name, _)| name == "token")
+        .map(|(_, value)| value.to_string());
+
+    header_token
+        .or(query_token)
+        .is_some_and(|given_token| is_token_equal(&given_token, token))
+}
+
+// Compares every byte, so the response time does not tell how much of the token matched
+fn is_token_equal(given_token: &str, token: &str) -> bool {
+    given_token.len() == token.len()
+        && given_token
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (given_byte, byte)| difference | (given_byte ^ byte))
+            == 0
+}
+
+fn control_response(my_app: &MyApp, request: ControlRequest) -> Response<Cursor<Vec<u8>>> {
+    let control_response = handle_control_request(my_app, request);
+
+    json_response(if control_response.ok { 200 } else { 409 }, &control_response)
+}
+
+// The wallpaper is stored as JPEG or PNG, the content type tells which one is served
+fn current_image_response() -> Response<Cursor<Vec<u8>>> {
+    let image = current_wallpaper_image_location()
+        .and_then(|location| Some((std::fs::read(&location).ok()?, location)));
+
+    match image {
+        Some((data, location)) => {
+            let content_type = match location.extension().and_then(|suffix| suffix.to_str()) {
+                Some("png") => "image/png",
+                _ => "image/jpeg",
+            };
+
+            Response::from_data(data)
+                .with_header(Header::from_bytes("Content-Type", content_type).unwrap())
+                .with_header(Header::from_bytes("Cache-Control", "no-store").unwrap())
+        }
+        None => json_response(404, &error_response("No wallpaper image fetched yet")),
+    }
+}
+
+fn error_response(message: &str) -> ControlResponse {
+    ControlResponse {
+        ok: false,
+        message: message.to_string(),
+        status: None,
+    }
+}
+
+fn json_response<T: Serialize + ?Sized>(
+    status_code: u16,
+    value: &T,
+) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(serde_json::to_string_pretty(value).unwrap())
+        .with_status_code(status_code)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
@@ -0,0 +1,24 @@
+use crate::{
+    app_config::AppConfig,
+    config_validation::{validate_url_address, ValidationIssue},
+    download_utils::download_image,
+    fetch_result::FetchError,
+    image_source::{FetchContext, FetchedImage, ImageSource},
+};
+
+// Fetches the image address directly
+pub struct Http;
+
+impl ImageSource for Http {
+    fn kind(&self) -> &'static str {
+        "Http"
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        download_image(context, &context.source_address)
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        validate_url_address(&config.image_address.lock().unwrap(), true, issues);
+    }
+}
@@ -0,0 +1,66 @@
+use std::io::Cursor;
+
+use image::{
+    codecs::gif::GifDecoder, AnimationDecoder, DynamicImage, ImageFormat, ImageOutputFormat,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{download_limits::DownloadLimits, fetch_result::FetchError};
+
+// Settings for inputs the wallpaper cannot use as they are. gif_frame picks the frame of
+// animated GIFs, 0 is the first one.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ImageConversion {
+    pub gif_frame: u32,
+}
+
+// Windows takes JPEG and PNG wallpapers, every other input format is stored as PNG. Returns
+// the format to store and its file suffix.
+pub fn wallpaper_format(image_format: ImageFormat) -> (ImageFormat, &'static str) {
+    match image_format {
+        ImageFormat::Jpeg => (ImageFormat::Jpeg, "jpg"),
+        _ => (ImageFormat::Png, "png"),
+    }
+}
+
+// Re-encodes a decoded image as PNG or JPEG
+pub fn encode_image(
+    image: &DynamicImage,
+    image_format: ImageFormat,
+) -> Result<Vec<u8>, FetchError> {
+    let mut encoded_image = Cursor::new(Vec::new());
+
+    let encode_result = match image_format {
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_to(&mut encoded_image, ImageOutputFormat::Jpeg(95)),
+        _ => image.write_to(&mut encoded_image, ImageOutputFormat::Png),
+    };
+
+    encode_result
+        .map(|_| encoded_image.into_inner())
+        .map_err(|e| FetchError::InvalidImage(format!("re-encoding failed - {}", e)))
+}
+
+pub fn decode_gif_frame(
+    body: &[u8],
+    download_limits: &DownloadLimits,
+    gif_frame: u32,
+) -> Result<DynamicImage, FetchError> {
+    let gif_decoder = GifDecoder::with_limits(Cursor::new(body), download_limits.image_limits())
+        .map_err(|e| FetchError::InvalidImage(e.to_string()))?;
+
+    let mut frame_count = 0;
+    for frame in gif_decoder.into_frames() {
+        let frame = frame.map_err(|e| FetchError::InvalidImage(e.to_string()))?;
+        if frame_count == gif_frame {
+            return Ok(DynamicImage::ImageRgba8(frame.into_buffer()));
+        }
+        frame_count += 1;
+    }
+
+    Err(FetchError::InvalidImage(format!(
+        "GIF frame {} requested, the image has {} frames",
+        gif_frame, frame_count
+    )))
+}
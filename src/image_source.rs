@@ -0,0 +1,100 @@
+use std::cell::OnceCell;
+
+use egui::Ui;
+
+use crate::{
+    app_config::AppConfig,
+    app_gui::MyApp,
+    command_source::Command,
+    config_validation::ValidationIssue,
+    download_utils::FetchCredentials,
+    feed_source::Feed,
+    fetch_result::{FetchError, ImageMetadata},
+    html_page_source::HtmlPage,
+    http_source::Http,
+    json_api_source::JsonApi,
+    local_folder_source::LocalFolder,
+    mjpeg_source::Mjpeg,
+};
+
+// Everything a source needs for one fetch. The source address is the configured image
+// address with its placeholders expanded, the previous metadata carries the cache validators
+// of the image applied as wallpaper.
+pub struct FetchContext<'a> {
+    pub config: &'a AppConfig,
+    pub source_address: String,
+    pub previous_metadata: ImageMetadata,
+    pub resolved_credentials: OnceCell<Result<FetchCredentials, FetchError>>,
+}
+
+impl FetchContext<'_> {
+    // Resolved by the first download of the fetch, so the secrets are decrypted at most once
+    // per fetch and sources without downloads never need the passphrase
+    pub fn credentials(&self) -> Result<&FetchCredentials, FetchError> {
+        self.resolved_credentials
+            .get_or_init(|| {
+                FetchCredentials::resolve(self.config).map_err(FetchError::Credentials)
+            })
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+}
+
+pub struct FetchedImage {
+    pub data: Vec<u8>,
+    pub metadata: ImageMetadata,
+}
+
+// A kind of image source, selected by the kind field of the config. Sources keep no state of
+// their own, their settings live in a section of AppConfig.
+pub trait ImageSource {
+    // The config value selecting this source
+    fn kind(&self) -> &'static str;
+
+    fn address_label(&self) -> &'static str {
+        "Image address:"
+    }
+
+    // Placeholder values expanded into URL addresses are percent-encoded
+    fn is_address_url(&self) -> bool {
+        true
+    }
+
+    // Returns None when the source reports the image unchanged since the previous fetch
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError>;
+
+    // Called once the fetched image was stored as the current wallpaper image
+    fn on_image_applied(&self, _context: &FetchContext, _metadata: &ImageMetadata) {}
+
+    // Checks the image address and the source's own config section
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>);
+
+    // Adds the rows of the source's own settings to the configuration grid
+    fn ui_add_settings(&self, _ui: &mut Ui, _my_app: &MyApp, _desired_width: f32) {}
+}
+
+// New source kinds are added here, the scheduler, the GUI and the validation pick them up
+pub fn image_sources() -> Vec<Box<dyn ImageSource>> {
+    vec![
+        Box::new(Http),
+        Box::new(HtmlPage),
+        Box::new(JsonApi),
+        Box::new(Feed),
+        Box::new(Mjpeg),
+        Box::new(LocalFolder),
+        Box::new(Command),
+    ]
+}
+
+pub fn find_image_source(kind: &str) -> Option<Box<dyn ImageSource>> {
+    image_sources()
+        .into_iter()
+        .find(|image_source| image_source.kind() == kind)
+}
+
+pub fn image_source_kinds() -> Vec<&'static str> {
+    image_sources()
+        .iter()
+        .map(|image_source| image_source.kind())
+        .collect()
+}
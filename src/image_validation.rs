@@ -0,0 +1,103 @@
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    download_limits::DownloadLimits,
+    fetch_result::FetchError,
+    image_conversion::{decode_gif_frame, ImageConversion},
+};
+
+// Per-source expectations a downloaded image has to meet before it is applied as wallpaper,
+// 0 disables a check
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct ImageRequirements {
+    pub min_width: u32,
+    pub min_height: u32,
+    pub min_aspect_ratio: f32,
+    pub max_aspect_ratio: f32,
+}
+
+// Fully decodes the payload in memory, so truncated or non-image payloads never reach the
+// wallpaper file. Animated GIFs are decoded at the configured frame.
+pub fn decode_and_validate_image(
+    body: &[u8],
+    download_limits: &DownloadLimits,
+    image_requirements: &ImageRequirements,
+    image_conversion: &ImageConversion,
+) -> Result<(DynamicImage, ImageFormat), FetchError> {
+    let mut image_reader = image::io::Reader::new(Cursor::new(body))
+        .with_guessed_format()
+        .map_err(|e| FetchError::InvalidImage(e.to_string()))?;
+    image_reader.limits(download_limits.image_limits());
+
+    let image_format = image_reader
+        .format()
+        .ok_or_else(|| FetchError::InvalidImage(String::from("unrecognized image format")))?;
+
+    if image_format == ImageFormat::Jpeg && !has_jpeg_end_marker(body) {
+        return Err(FetchError::InvalidImage(String::from("truncated JPEG data")));
+    }
+
+    if image_format == ImageFormat::Avif && !cfg!(feature = "avif") {
+        return Err(FetchError::InvalidImage(String::from(
+            "AVIF images require a build with the avif feature",
+        )));
+    }
+
+    let image = if image_format == ImageFormat::Gif && image_conversion.gif_frame > 0 {
+        decode_gif_frame(body, download_limits, image_conversion.gif_frame)?
+    } else {
+        image_reader
+            .decode()
+            .map_err(|e| FetchError::InvalidImage(e.to_string()))?
+    };
+
+    check_image_requirements(image.width(), image.height(), image_requirements)?;
+
+    Ok((image, image_format))
+}
+
+fn has_jpeg_end_marker(body: &[u8]) -> bool {
+    let trimmed_length = body
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |position| position + 1);
+
+    body[..trimmed_length].ends_with(&[0xFF, 0xD9])
+}
+
+fn check_image_requirements(
+    width: u32,
+    height: u32,
+    image_requirements: &ImageRequirements,
+) -> Result<(), FetchError> {
+    if width < image_requirements.min_width || height < image_requirements.min_height {
+        return Err(FetchError::InvalidImage(format!(
+            "{}x{} is below the required minimum of {}x{}",
+            width, height, image_requirements.min_width, image_requirements.min_height
+        )));
+    }
+
+    let aspect_ratio = width as f32 / height.max(1) as f32;
+
+    if image_requirements.min_aspect_ratio > 0.0 && aspect_ratio < image_requirements.min_aspect_ratio
+    {
+        return Err(FetchError::InvalidImage(format!(
+            "aspect ratio {:.2} is below the required minimum of {:.2}",
+            aspect_ratio, image_requirements.min_aspect_ratio
+        )));
+    }
+
+    if image_requirements.max_aspect_ratio > 0.0 && aspect_ratio > image_requirements.max_aspect_ratio
+    {
+        return Err(FetchError::InvalidImage(format!(
+            "aspect ratio {:.2} is above the allowed maximum of {:.2}",
+            aspect_ratio, image_requirements.max_aspect_ratio
+        )));
+    }
+
+    Ok(())
+}
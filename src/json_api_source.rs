@@ -0,0 +1,144 @@
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    app_config::AppConfig,
+    app_gui::{ui_add_config_label, MyApp},
+    config_validation::{error, validate_url_address, ValidationIssue},
+    download_utils::{download, download_image},
+    fetch_result::{FetchError, ImageMetadata},
+    html_page_source::resolve_url,
+    image_source::{FetchContext, FetchedImage, ImageSource},
+    json_path::{select_first, validate_json_path, value_to_string},
+    source_auth::mask_url_credentials,
+};
+
+// Settings of JSON endpoints describing the image, e.g. picture-of-the-day services. The paths
+// are JSONPath expressions, title and credit are optional and shown as the image caption.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct JsonApiSource {
+    pub image_url_path: String,
+    pub title_path: String,
+    pub credit_path: String,
+}
+
+impl JsonApiSource {
+    pub fn extract_image_url(
+        &self,
+        response: &[u8],
+        api_url: &str,
+    ) -> Result<(String, ImageMetadata), String> {
+        let document: Value = serde_json::from_slice(response)
+            .map_err(|e| format!("the response is not valid JSON ({})", e))?;
+
+        let image_url = select_first(&document, &self.image_url_path)?
+            .map(value_to_string)
+            .filter(|image_url| !image_url.trim().is_empty())
+            .ok_or_else(|| format!("nothing matches {}", self.image_url_path))?;
+
+        let image_metadata = ImageMetadata {
+            title: self.optional_value(&document, &self.title_path)?,
+            credit: self.optional_value(&document, &self.credit_path)?,
+            ..ImageMetadata::default()
+        };
+
+        Ok((resolve_url(api_url, image_url.trim())?, image_metadata))
+    }
+
+    fn optional_value(&self, document: &Value, path: &str) -> Result<String, String> {
+        if path.is_empty() {
+            return Ok(String::from(""));
+        }
+
+        Ok(select_first(document, path)?
+            .map(value_to_string)
+            .unwrap_or_default())
+    }
+}
+
+// Looks up the image URL, title and credit in the JSON response of the image address
+pub struct JsonApi;
+
+impl ImageSource for JsonApi {
+    fn kind(&self) -> &'static str {
+        "JsonApi"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "API address:"
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        let response = download(context, &context.source_address, false)?;
+        let json_api_source = context.config.json_api.lock().unwrap().clone();
+
+        let (image_url, image_metadata) = json_api_source
+            .extract_image_url(&response, &context.source_address)
+            .map_err(FetchError::ImageNotFound)?;
+
+        log::info!("Image URL found in the JSON response: {}", mask_url_credentials(&image_url));
+
+        Ok(download_image(context, &image_url)?.map(|fetched_image| FetchedImage {
+            metadata: ImageMetadata {
+                title: image_metadata.title,
+                credit: image_metadata.credit,
+                ..fetched_image.metadata
+            },
+            ..fetched_image
+        }))
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        validate_url_address(&config.image_address.lock().unwrap(), false, issues);
+        validate_json_api_source(&config.json_api.lock().unwrap(), issues);
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, desired_width: f32) {
+        let json_api_handle = &mut *my_app.config.json_api.lock().unwrap();
+
+        for (label, key, json_path) in [
+            (
+                "Image URL path:",
+                "json_api.image_url_path",
+                &mut json_api_handle.image_url_path,
+            ),
+            ("Title path:", "json_api.title_path", &mut json_api_handle.title_path),
+            ("Credit path:", "json_api.credit_path", &mut json_api_handle.credit_path),
+        ] {
+            let is_overridden = ui_add_config_label(ui, my_app, label, key);
+            ui.add_enabled(
+                !is_overridden,
+                egui::TextEdit::singleline(json_path)
+                    .hint_text("JSONPath, e.g. $.images[0].url")
+                    .desired_width(desired_width),
+            );
+
+            ui.end_row();
+        }
+    }
+}
+
+fn validate_json_api_source(json_api_source: &JsonApiSource, issues: &mut Vec<ValidationIssue>) {
+    if json_api_source.image_url_path.is_empty() {
+        issues.push(error(
+            "json_api.image_url_path",
+            "A JSONPath to the image URL is required, e.g. $.images[0].url",
+        ));
+    }
+
+    for (field, json_path) in [
+        ("json_api.image_url_path", &json_api_source.image_url_path),
+        ("json_api.title_path", &json_api_source.title_path),
+        ("json_api.credit_path", &json_api_source.credit_path),
+    ] {
+        if json_path.is_empty() {
+            continue;
+        }
+
+        if let Err(e) = validate_json_path(json_path) {
+            issues.push(error(field, &e));
+        }
+    }
+}
@@ -0,0 +1,259 @@
+use serde_json::Value;
+
+// The JSONPath subset needed to pick values out of API responses: $ root, .key / ['key'],
+// [index] (negative counts from the end), [*] / .* wildcards and ..key recursive descent
+#[derive(Debug, PartialEq, Clone)]
+enum PathSegment {
+    Key(String),
+    Index(i64),
+    Wildcard,
+    RecursiveKey(String),
+}
+
+pub fn validate_json_path(path: &str) -> Result<(), String> {
+    parse_json_path(path).map(|_| ())
+}
+
+// Returns the first value matching the path in document order
+pub fn select_first<'a>(value: &'a Value, path: &str) -> Result<Option<&'a Value>, String> {
+    let mut matches = vec![value];
+
+    for segment in parse_json_path(path)? {
+        matches = matches
+            .into_iter()
+            .flat_map(|matched_value| select_segment(matched_value, &segment))
+            .collect();
+    }
+
+    Ok(matches.into_iter().next())
+}
+
+fn select_segment<'a>(value: &'a Value, segment: &PathSegment) -> Vec<&'a Value> {
+    match segment {
+        PathSegment::Key(key) => value.get(key).into_iter().collect(),
+        PathSegment::Index(index) => match value.as_array() {
+            Some(array) => {
+                let index = if *index < 0 {
+                    array.len() as i64 + index
+                } else {
+                    *index
+                };
+                usize::try_from(index)
+                    .ok()
+                    .and_then(|index| array.get(index))
+                    .into_iter()
+                    .collect()
+            }
+            None => Vec::new(),
+        },
+        PathSegment::Wildcard => match value {
+            Value::Array(array) => array.iter().collect(),
+            Value::Object(object) => object.values().collect(),
+            _ => Vec::new(),
+        },
+        PathSegment::RecursiveKey(key) => {
+            let mut found_values = Vec::new();
+            collect_recursive(value, key, &mut found_values);
+            found_values
+        }
+    }
+}
+
+fn collect_recursive<'a>(value: &'a Value, key: &str, found_values: &mut Vec<&'a Value>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(found_value) = object.get(key) {
+                found_values.push(found_value);
+            }
+            for child in object.values() {
+                collect_recursive(child, key, found_values);
+            }
+        }
+        Value::Array(array) => {
+            for child in array {
+                collect_recursive(child, key, found_values);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn parse_json_path(path: &str) -> Result<Vec<PathSegment>, String> {
+    let path = path.trim();
+    let mut rest = path
+        .strip_prefix('$')
+        .ok_or_else(|| format!("JSONPath \"{}\" must start with $", path))?;
+    let mut segments = Vec::new();
+
+    while !rest.is_empty() {
+        if let Some(after_dots) = rest.strip_prefix("..") {
+            let (key, after_key) = split_key(after_dots);
+            if key.is_empty() {
+                return Err(format!("JSONPath \"{}\" is missing a key after ..", path));
+            }
+            segments.push(PathSegment::RecursiveKey(key.to_string()));
+            rest = after_key;
+        } else if let Some(after_dot) = rest.strip_prefix('.') {
+            let (key, after_key) = split_key(after_dot);
+            segments.push(match key {
+                "" => return Err(format!("JSONPath \"{}\" is missing a key after .", path)),
+                "*" => PathSegment::Wildcard,
+                _ => PathSegment::Key(key.to_string()),
+            });
+            rest = after_key;
+        } else if let Some(after_bracket) = rest.strip_prefix('[') {
+            let (selector, after_selector) = after_bracket
+                .split_once(']')
+                .ok_or_else(|| format!("JSONPath \"{}\" has an unclosed [", path))?;
+            let selector = selector.trim();
+
+            segments.push(if selector == "*" {
+                PathSegment::Wildcard
+            } else if let Some(quoted_key) = strip_quotes(selector) {
+                PathSegment::Key(quoted_key.to_string())
+            } else {
+                PathSegment::Index(selector.parse::<i64>().map_err(|_| {
+                    format!("JSONPath \"{}\" has an invalid index [{}]", path, selector)
+                })?)
+            });
+            rest = after_selector;
+        } else {
+            return Err(format!(
+                "JSONPath \"{}\" has an unexpected \"{}\"",
+                path, rest
+            ));
+        }
+    }
+
+    Ok(segments)
+}
+
+fn split_key(path: &str) -> (&str, &str) {
+    let key_end = path.find(['.', '[']).unwrap_or(path.len());
+    path.split_at(key_end)
+}
+
+fn strip_quotes(selector: &str) -> Option<&str> {
+    selector
+        .strip_prefix('\'')
+        .and_then(|selector| selector.strip_suffix('\''))
+        .or_else(|| {
+            selector
+                .strip_prefix('"')
+                .and_then(|selector| selector.strip_suffix('"'))
+        })
+}
+
+// Strings are used as they are, other scalars in their JSON notation
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(string) => string.clone(),
+        Value::Null => String::from(""),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{parse_json_path, select_first, value_to_string, PathSegment};
+
+    fn document() -> Value {
+        json!({
+            "data": {
+                "images": [
+                    { "url": "https://example.com/1.jpg", "size": 1 },
+                    { "url": "https://example.com/2.jpg", "size": 2 }
+                ],
+                "a.b": "dotted",
+                "copyright": { "holder": "NASA" }
+            },
+            "count": 2
+        })
+    }
+
+    #[test]
+    fn parses_dot_and_bracket_segments() {
+        assert_eq!(
+            parse_json_path("$.data['images'][0][\"url\"]"),
+            Ok(vec![
+                PathSegment::Key(String::from("data")),
+                PathSegment::Key(String::from("images")),
+                PathSegment::Index(0),
+                PathSegment::Key(String::from("url")),
+            ])
+        );
+        assert_eq!(
+            parse_json_path(" $..url[*].* "),
+            Ok(vec![
+                PathSegment::RecursiveKey(String::from("url")),
+                PathSegment::Wildcard,
+                PathSegment::Wildcard,
+            ])
+        );
+        assert_eq!(parse_json_path("$"), Ok(Vec::new()));
+        assert_eq!(parse_json_path("$[-1]"), Ok(vec![PathSegment::Index(-1)]));
+    }
+
+    #[test]
+    fn rejects_malformed_paths() {
+        assert!(parse_json_path("data.url").is_err());
+        assert!(parse_json_path("$.").is_err());
+        assert!(parse_json_path("$..").is_err());
+        assert!(parse_json_path("$..[0]").is_err());
+        assert!(parse_json_path("$[0").is_err());
+        assert!(parse_json_path("$[first]").is_err());
+        assert!(parse_json_path("$['unclosed]").is_err());
+        assert!(parse_json_path("$data").is_err());
+    }
+
+    #[test]
+    fn selects_keys_and_indexes() {
+        let document = document();
+
+        assert_eq!(
+            select_first(&document, "$.data.images[1].url"),
+            Ok(Some(&json!("https://example.com/2.jpg")))
+        );
+        assert_eq!(
+            select_first(&document, "$.data.images[-1].size"),
+            Ok(Some(&json!(2)))
+        );
+        assert_eq!(
+            select_first(&document, "$.data['a.b']"),
+            Ok(Some(&json!("dotted")))
+        );
+        assert_eq!(select_first(&document, "$.data.images[2]"), Ok(None));
+        assert_eq!(select_first(&document, "$.data.images[-3]"), Ok(None));
+        assert_eq!(select_first(&document, "$.count[0]"), Ok(None));
+        assert_eq!(select_first(&document, "$.missing.url"), Ok(None));
+    }
+
+    #[test]
+    fn selects_the_first_match_in_document_order() {
+        let document = document();
+
+        assert_eq!(
+            select_first(&document, "$..url"),
+            Ok(Some(&json!("https://example.com/1.jpg")))
+        );
+        assert_eq!(
+            select_first(&document, "$.data.images[*].url"),
+            Ok(Some(&json!("https://example.com/1.jpg")))
+        );
+        assert_eq!(
+            select_first(&document, "$..holder"),
+            Ok(Some(&json!("NASA")))
+        );
+        assert_eq!(select_first(&document, "$.count.*"), Ok(None));
+    }
+
+    #[test]
+    fn converts_values_to_strings() {
+        assert_eq!(value_to_string(&json!("text")), "text");
+        assert_eq!(value_to_string(&json!(null)), "");
+        assert_eq!(value_to_string(&json!(42)), "42");
+        assert_eq!(value_to_string(&json!(true)), "true");
+    }
+}
@@ -0,0 +1,230 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Local};
+use egui::Ui;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    app_gui::{ui_add_config_label, MyApp},
+    config_validation::{error, ValidationIssue},
+    fetch_result::{CacheValidators, FetchError, ImageMetadata},
+    image_source::{FetchContext, FetchedImage, ImageSource},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum LocalFolderSelection {
+    #[default]
+    Newest,
+    Random,
+    Sequential,
+}
+
+// Settings of folders of images (e.g. a synced photo folder). Newest picks the most recently
+// modified image, Random any other image than the current one, Sequential the next image in
+// file name order.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct LocalFolderSource {
+    pub selection: LocalFolderSelection,
+    pub include_subfolders: bool,
+}
+
+struct ImageFile {
+    path: PathBuf,
+    modified_time: SystemTime,
+}
+
+// Picks an image from the folder at the image address
+pub struct LocalFolder;
+
+impl ImageSource for LocalFolder {
+    fn kind(&self) -> &'static str {
+        "LocalFolder"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "Folder:"
+    }
+
+    fn is_address_url(&self) -> bool {
+        false
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        let local_folder_source = context.config.local_folder.lock().unwrap().clone();
+
+        let mut image_files = Vec::new();
+        collect_image_files(
+            Path::new(&context.source_address),
+            local_folder_source.include_subfolders,
+            &mut image_files,
+        )
+        .map_err(|e| FetchError::FileAccess(format!("{} ({})", context.source_address, e)))?;
+
+        if image_files.is_empty() {
+            return Err(FetchError::ImageNotFound(format!(
+                "{} contains no {} files",
+                context.source_address,
+                AppConstants::SUPPORTED_IMAGE_SUFFIXES.join(" / ")
+            )));
+        }
+
+        image_files.sort_by(|a, b| a.path.cmp(&b.path));
+        let previous_path = &context.previous_metadata.entry_id;
+
+        let selected_file = match local_folder_source.selection {
+            LocalFolderSelection::Newest => image_files
+                .iter()
+                .max_by_key(|image_file| image_file.modified_time)
+                .unwrap(),
+            LocalFolderSelection::Random => {
+                let candidates: Vec<&ImageFile> = image_files
+                    .iter()
+                    .filter(|image_file| {
+                        image_files.len() == 1
+                            || image_file.path.to_string_lossy() != *previous_path
+                    })
+                    .collect();
+                candidates.choose(&mut rand::thread_rng()).copied().unwrap()
+            }
+            LocalFolderSelection::Sequential => image_files
+                .iter()
+                .find(|image_file| {
+                    image_file.path.to_string_lossy().as_ref() > previous_path.as_str()
+                })
+                .unwrap_or(&image_files[0]),
+        };
+
+        let selected_path = selected_file.path.to_string_lossy().to_string();
+        let modified_time: DateTime<Local> = selected_file.modified_time.into();
+        let cache_validators = CacheValidators {
+            url: selected_path.clone(),
+            etag: String::from(""),
+            last_modified: modified_time.to_rfc2822(),
+        };
+
+        if cache_validators == context.previous_metadata.cache_validators {
+            return Ok(None);
+        }
+
+        let max_download_size = context.config.download_limits.lock().unwrap().max_download_size;
+        let data = read_image_file(&selected_file.path, max_download_size)?;
+
+        log::info!("Image selected from the folder: {}", selected_path);
+
+        Ok(Some(FetchedImage {
+            data,
+            metadata: ImageMetadata {
+                title: selected_file
+                    .path
+                    .file_name()
+                    .map(|file_name| file_name.to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                capture_time: Some(modified_time),
+                entry_id: selected_path,
+                cache_validators,
+                ..ImageMetadata::default()
+            },
+        }))
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        let folder = config.image_address.lock().unwrap().clone();
+
+        if folder.is_empty() {
+            issues.push(error("image_address", "A folder is required"));
+        } else if !folder.contains('{') && !Path::new(&folder).is_dir() {
+            issues.push(error("image_address", &format!("{} is not a folder", folder)));
+        }
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, _desired_width: f32) {
+        let local_folder_handle = &mut *my_app.config.local_folder.lock().unwrap();
+
+        let is_overridden =
+            ui_add_config_label(ui, my_app, "Image selection:", "local_folder.selection");
+
+        ui.add_enabled_ui(!is_overridden, |ui| {
+            egui::ComboBox::from_id_source("local_folder_selection")
+                .selected_text(format!("{:?}", local_folder_handle.selection))
+                .show_ui(ui, |ui| {
+                    for selection in [
+                        LocalFolderSelection::Newest,
+                        LocalFolderSelection::Random,
+                        LocalFolderSelection::Sequential,
+                    ] {
+                        ui.selectable_value(
+                            &mut local_folder_handle.selection,
+                            selection,
+                            format!("{:?}", selection),
+                        );
+                    }
+                });
+        });
+
+        ui.end_row();
+
+        let is_overridden = ui_add_config_label(
+            ui,
+            my_app,
+            "Include subfolders:",
+            "local_folder.include_subfolders",
+        );
+        ui.add_enabled(
+            !is_overridden,
+            egui::Checkbox::new(&mut local_folder_handle.include_subfolders, ""),
+        );
+
+        ui.end_row();
+    }
+}
+
+// Reads an image file, applying the download size limit
+pub fn read_image_file(path: &Path, max_download_size: u64) -> Result<Vec<u8>, FetchError> {
+    let file_size = std::fs::metadata(path)
+        .map_err(|e| FetchError::FileAccess(format!("{} ({})", path.display(), e)))?
+        .len();
+    if file_size > max_download_size {
+        return Err(FetchError::TooLarge(max_download_size));
+    }
+
+    std::fs::read(path).map_err(|e| FetchError::FileAccess(format!("{} ({})", path.display(), e)))
+}
+
+fn collect_image_files(
+    folder: &Path,
+    include_subfolders: bool,
+    image_files: &mut Vec<ImageFile>,
+) -> std::io::Result<()> {
+    for dir_entry in std::fs::read_dir(folder)? {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        let path = dir_entry.path();
+
+        if metadata.is_dir() {
+            if include_subfolders {
+                collect_image_files(&path, include_subfolders, image_files)?;
+            }
+            continue;
+        }
+
+        let file_name = path.to_string_lossy().to_ascii_lowercase();
+        if AppConstants::SUPPORTED_IMAGE_SUFFIXES
+            .iter()
+            .any(|suffix| file_name.ends_with(suffix))
+        {
+            image_files.push(ImageFile {
+                path,
+                modified_time: metadata.modified()?,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -1,38 +1,239 @@
+use std::{collections::BTreeMap, sync::Mutex};
+
+use log::LevelFilter;
 use log4rs::{
-    append::file::FileAppender,
-    config::{Appender, Root},
+    append::{
+        console::{ConsoleAppender, Target},
+        rolling_file::{
+            policy::compound::{
+                roll::fixed_window::FixedWindowRoller,
+                trigger::{
+                    size::SizeTrigger,
+                    time::{TimeTrigger, TimeTriggerConfig},
+                    Trigger,
+                },
+                CompoundPolicy,
+            },
+            RollingFileAppender,
+        },
+    },
+    config::{Appender, Logger, Root},
     encode::pattern::PatternEncoder,
-    Config,
+    Config, Handle,
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{app_constants::AppConstants, windows_os_utils::build_absolute_path};
 
+const LOG_PATTERN: &str = "{d(%Y-%m-%d %H:%M:%S.%3f)(local)} {l} {t} - {m}{n}";
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 6] = [
+        LogLevel::Off,
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Trace,
+    ];
+
+    fn to_level_filter(self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+// Size rolls the log file when it grows past max_file_size_mb, the others at the start of the
+// hour, day or week
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum LogRotation {
+    #[default]
+    Size,
+    Hourly,
+    Daily,
+    Weekly,
+}
+
+// module_levels overrides the level for single modules or crates, e.g. "pics2wall::download_utils"
+// = "Debug" or "tiny_http" = "Warn". retention_count is the number of rolled files kept next to
+// the current one (log/pics2wall.1.log is the newest). log_to_stderr additionally writes to
+// stderr for service wrappers capturing the output, journald does not exist on Windows.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub level: LogLevel,
+    pub module_levels: BTreeMap<String, LogLevel>,
+    pub rotation: LogRotation,
+    pub max_file_size_mb: u64,
+    pub retention_count: u32,
+    pub log_to_stderr: bool,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::Info,
+            module_levels: BTreeMap::new(),
+            rotation: LogRotation::Size,
+            max_file_size_mb: 10,
+            retention_count: 5,
+            log_to_stderr: false,
+        }
+    }
+}
+
+// The handle of the running logger with the config it was built from
+static LOGGING: Mutex<Option<(Handle, LoggingConfig)>> = Mutex::new(None);
+
+// Starts with the default config, as logging is needed before the config file is loaded
 pub fn start_logging() {
-    // set up logging
+    let logging_config = LoggingConfig::default();
+    let (log_config, log_config_errors) = build_log_config(&logging_config);
+
+    match log4rs::init_config(log_config) {
+        Ok(handle) => *LOGGING.lock().unwrap() = Some((handle, logging_config)),
+        Err(e) => eprintln!("Logging could not be started - {}", e),
+    }
+
+    for log_config_error in log_config_errors {
+        log::error!("{}", log_config_error);
+    }
+}
+
+// Called on every scheduler tick and on changes in the GUI, the appenders and filters are only
+// rebuilt when the config differs from the applied one
+pub fn apply_logging_config(logging_config: &LoggingConfig) {
+    let mut logging = LOGGING.lock().unwrap();
+
+    let (handle, applied_logging_config) = match &mut *logging {
+        Some(logging) => logging,
+        None => return,
+    };
 
-    let logfile = FileAppender::builder()
-        .encoder(Box::new(PatternEncoder::new(
-            "{d(%Y-%m-%d %H:%M:%S.%3f)(local)} {l} {t} - {m}{n}",
-        )))
-        .build(AppConstants::LOG_FILE_LOCATION.to_string() + AppConstants::APP_NAME + ".log")
-        .unwrap();
+    if applied_logging_config == logging_config {
+        return;
+    }
 
-    let log_config = Config::builder()
-        .appender(Appender::builder().build("logfile", Box::new(logfile)))
+    let (log_config, log_config_errors) = build_log_config(logging_config);
+    handle.set_config(log_config);
+    *applied_logging_config = logging_config.clone();
+
+    drop(logging);
+
+    for log_config_error in log_config_errors {
+        log::error!("{}", log_config_error);
+    }
+    log::info!("Logging config applied, level {:?}", logging_config.level);
+}
+
+// Never fails, without the log file it logs to stderr only. The returned errors are logged by
+// the caller once the config is in place.
+fn build_log_config(logging_config: &LoggingConfig) -> (Config, Vec<String>) {
+    let mut errors = Vec::new();
+    let mut config_builder = Config::builder();
+    let mut root_builder = Root::builder();
+
+    match build_file_appender(logging_config) {
+        Ok(file_appender) => {
+            config_builder = config_builder
+                .appender(Appender::builder().build("logfile", Box::new(file_appender)));
+            root_builder = root_builder.appender("logfile");
+        }
+        Err(e) => errors.push(format!("{}, logging to stderr only", e)),
+    }
+
+    if logging_config.log_to_stderr || !errors.is_empty() {
+        let stderr_appender = ConsoleAppender::builder()
+            .encoder(Box::new(PatternEncoder::new(LOG_PATTERN)))
+            .target(Target::Stderr)
+            .build();
+        config_builder =
+            config_builder.appender(Appender::builder().build("stderr", Box::new(stderr_appender)));
+        root_builder = root_builder.appender("stderr");
+    }
+
+    for (module, level) in &logging_config.module_levels {
+        config_builder =
+            config_builder.logger(Logger::builder().build(module, level.to_level_filter()));
+    }
+
+    // invalid module filters are dropped, the rest of the config still applies
+    let (log_config, config_errors) =
+        config_builder.build_lossy(root_builder.build(logging_config.level.to_level_filter()));
+    errors.extend(
+        config_errors
+            .errors()
+            .iter()
+            .map(|e| format!("Log filter ignored - {}", e)),
+    );
+
+    (log_config, errors)
+}
+
+fn build_file_appender(logging_config: &LoggingConfig) -> Result<RollingFileAppender, String> {
+    std::fs::create_dir_all(AppConstants::LOG_FILE_LOCATION)
+        .map_err(|e| format!("Log folder could not be created - {}", e))?;
+
+    let trigger: Box<dyn Trigger> = match logging_config.rotation {
+        LogRotation::Size => Box::new(SizeTrigger::new(
+            logging_config.max_file_size_mb.max(1) * 1024 * 1024,
+        )),
+        LogRotation::Hourly => build_time_trigger("1 hour")?,
+        LogRotation::Daily => build_time_trigger("1 day")?,
+        LogRotation::Weekly => build_time_trigger("1 week")?,
+    };
+
+    let roller = FixedWindowRoller::builder()
+        .base(1)
+        .build(
+            &(AppConstants::LOG_FILE_LOCATION.to_string() + AppConstants::APP_NAME + ".{}.log"),
+            logging_config.retention_count.max(1),
+        )
+        .map_err(|e| format!("Log rotation could not be set up - {}", e))?;
+
+    RollingFileAppender::builder()
+        .encoder(Box::new(PatternEncoder::new(LOG_PATTERN)))
         .build(
-            Root::builder()
-                .appender("logfile")
-                .build(log::LevelFilter::Info),
-        );
+            log_file_location(),
+            Box::new(CompoundPolicy::new(trigger, Box::new(roller))),
+        )
+        .map_err(|e| format!("Log file could not be opened - {}", e))
+}
+
+// The fields of the time trigger config are private, it can only be deserialized. Modulated
+// intervals roll at the start of the hour, day or week instead of counting from the app start.
+fn build_time_trigger(interval: &str) -> Result<Box<dyn Trigger>, String> {
+    let time_trigger_config: TimeTriggerConfig =
+        serde_json::from_value(serde_json::json!({ "interval": interval, "modulate": true }))
+            .map_err(|e| format!("Log rotation could not be set up - {}", e))?;
+
+    Ok(Box::new(TimeTrigger::new(time_trigger_config)))
+}
 
-    let _config_handle = log4rs::init_config(log_config.unwrap());
+fn log_file_location() -> String {
+    AppConstants::LOG_FILE_LOCATION.to_string() + AppConstants::APP_NAME + ".log"
 }
 
 pub fn open_logs_externally() {
     match std::process::Command::new("notepad")
-        .arg(build_absolute_path(
-            &(AppConstants::LOG_FILE_LOCATION.to_string() + AppConstants::APP_NAME + ".log"),
-        ))
+        .arg(build_absolute_path(&log_file_location()))
         .output()
     {
         Ok(_) => {}
@@ -1,27 +1,117 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use crate::log_utils::start_logging;
-use crate::common_utils::{fetch_pic};
-use crate::windows_os_utils::{set_pic_as_wallpaper};
+use crate::app_cli::{run_cli_command, LaunchOptions};
+use crate::log_utils::{apply_logging_config, start_logging};
+use crate::app_scheduler::start_scheduler;
+use crate::config_format_utils::{active_profile, config_file_exists, set_active_profile};
+use crate::config_overrides::ConfigOverrides;
+use crate::single_instance::{acquire_instance_lock, send_handoff_request, HandoffRequest};
 use app_constants::AppConstants;
 use app_gui::MyApp;
 use egui::Vec2;
-use std::{fs::create_dir, io::ErrorKind, thread, time};
+use std::{
+    fs::{create_dir, create_dir_all},
+    io::ErrorKind,
+};
 
+mod app_cli;
 mod app_config;
 mod app_constants;
 mod app_gui;
+mod app_scheduler;
 mod log_utils;
 mod windows_os_utils;
+mod command_source;
 mod common_utils;
+mod config_format_utils;
+mod control_api;
+mod config_overrides;
+mod config_validation;
+mod download_limits;
+mod download_utils;
+mod duration_utils;
+mod exif_utils;
+mod feed_source;
+mod fetch_result;
+mod html_page_source;
+mod image_conversion;
+mod http_server;
+mod http_source;
+mod image_source;
+mod image_validation;
+mod json_api_source;
+mod json_path;
+mod local_folder_source;
+mod metrics;
+mod mjpeg_source;
+mod network_config;
+mod preset_catalog;
+mod secrets_utils;
+mod single_instance;
+mod source_auth;
+mod url_template;
 
 fn main() {
     start_logging();
     log::info!("----------- App started -----------");
     // env::set_var("RUST_BACKTRACE", "1");
 
-    let my_app = MyApp::default();
-    let mut secs_since_last_update = 0;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let launch_options = LaunchOptions::parse(&args);
+
+    if let Some(profile) = &launch_options.profile {
+        match set_active_profile(profile) {
+            Ok(_) => {}
+            Err(e) => log::error!("{}, the main config file is used", e),
+        }
+    }
+
+    let config_overrides = ConfigOverrides::load(&args);
+
+    if let Some(exit_code) = run_cli_command(&args, &config_overrides) {
+        std::process::exit(exit_code);
+    }
+
+    // a second instance would fight the running one over the config and the wallpaper files
+    let _instance_lock = match acquire_instance_lock() {
+        Ok(Some(instance_lock)) => Some(instance_lock),
+        Ok(None) => {
+            log::info!("Another instance is running, handing the launch over to it");
+            for request in launch_options.handoff_requests() {
+                match send_handoff_request(&request) {
+                    Ok(_) => {}
+                    Err(e) => log::error!("{}", e),
+                }
+            }
+            std::process::exit(0);
+        }
+        Err(e) => {
+            log::error!("{}, starting without the single instance check", e);
+            None
+        }
+    };
+
+    if !active_profile().is_empty() {
+        match create_dir_all(AppConstants::PROFILES_DIRECTORY) {
+            Ok(_) => {}
+            Err(e) => log::error!("Could not create the profiles folder - {}", e),
+        }
+        if !config_file_exists() {
+            log::warn!("Profile {} has no config file yet, saving creates it", active_profile());
+        }
+    }
+
+    // picked up by the scheduler like the requests of a second launch
+    if launch_options.is_fetch_now_requested {
+        match send_handoff_request(&HandoffRequest::FetchNow) {
+            Ok(_) => {}
+            Err(e) => log::error!("{}", e),
+        }
+    }
+
+    let my_app = MyApp::new(config_overrides);
+
+    apply_logging_config(&my_app.config.logging.lock().unwrap());
 
     match create_dir("data") {
         Ok(_) => {}
@@ -35,52 +125,7 @@ fn main() {
         },
     };
 
-    let mut thread_my_app_clone = my_app.clone();
-
-    thread::spawn(move || loop {
-        log::info!("Timer tick");
-
-        if *thread_my_app_clone
-            .config
-            .is_auto_update_active
-            .lock()
-            .unwrap()
-        {
-            log::info!("Auto-update cycle started");
-            if secs_since_last_update
-                >= *thread_my_app_clone
-                    .config
-                    .auto_update_interval
-                    .lock()
-                    .unwrap()
-                || secs_since_last_update == 0
-            {
-                fetch_pic(&mut thread_my_app_clone);
-                set_pic_as_wallpaper(&thread_my_app_clone);
-                secs_since_last_update = 0;
-            }
-
-            secs_since_last_update += AppConstants::TICK_INTERVAL;
-
-            *thread_my_app_clone.status.lock().unwrap() = String::from("Idle");
-        } else {
-            secs_since_last_update = 0;
-        }
-
-        if *thread_my_app_clone
-            .is_test_image_fetch_requested
-            .lock()
-            .unwrap()
-        {
-            fetch_pic(&mut thread_my_app_clone);
-            *thread_my_app_clone
-                .is_test_image_fetch_requested
-                .lock()
-                .unwrap() = false;
-        }
-
-        thread::sleep(time::Duration::from_secs(AppConstants::TICK_INTERVAL));
-    });
+    start_scheduler(my_app.clone());
 
     let icon = image::open("resources/icon.png")
         .expect("Failed to open icon path")
@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_gui::MyApp,
+    common_utils::write_file_atomically,
+    fetch_result::FetchResult,
+};
+
+const FETCH_DURATION_BUCKETS: [f64; 9] = [0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+const DECODE_DURATION_BUCKETS: [f64; 8] = [0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+// textfile is the location of a .prom file for the textfile collector of the node exporter
+// (e.g. C:\Program Files\windows_exporter\textfile_inputs\pics2wall.prom), it is rewritten on
+// every update tick. Empty disables it, the metrics are also served by the HTTP server.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub textfile: String,
+}
+
+// Fetch metrics are labeled with the source kind and the (unexpanded) image address without its
+// query string
+type SourceLabels = (String, String);
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    buckets: &'static [f64],
+    bucket_counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &'static [f64]) -> Self {
+        Self {
+            buckets,
+            bucket_counts: vec![0; buckets.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bucket, bucket_count) in self.buckets.iter().zip(self.bucket_counts.iter_mut()) {
+            if value <= *bucket {
+                *bucket_count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn write_to(&self, exposition: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+
+        for (bucket, bucket_count) in self.buckets.iter().zip(&self.bucket_counts) {
+            let _ = writeln!(
+                exposition,
+                "{}_bucket{{{}{}le=\"{}\"}} {}",
+                name, labels, separator, bucket, bucket_count
+            );
+        }
+        let _ = writeln!(
+            exposition,
+            "{}_bucket{{{}{}le=\"+Inf\"}} {}",
+            name, labels, separator, self.count
+        );
+        let _ = writeln!(exposition, "{}_sum{} {}", name, braced(labels), self.sum);
+        let _ = writeln!(exposition, "{}_count{} {}", name, braced(labels), self.count);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    fetch_attempts: BTreeMap<SourceLabels, u64>,
+    fetch_successes: BTreeMap<SourceLabels, u64>,
+    fetch_not_modified: BTreeMap<SourceLabels, u64>,
+    fetch_failures: BTreeMap<(SourceLabels, &'static str), u64>,
+    downloaded_bytes: BTreeMap<SourceLabels, u64>,
+    fetch_durations: BTreeMap<SourceLabels, Histogram>,
+    decode_durations: Histogram,
+    wallpaper_apply_failures: u64,
+    last_success_times: BTreeMap<SourceLabels, SystemTime>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            fetch_attempts: BTreeMap::new(),
+            fetch_successes: BTreeMap::new(),
+            fetch_not_modified: BTreeMap::new(),
+            fetch_failures: BTreeMap::new(),
+            downloaded_bytes: BTreeMap::new(),
+            fetch_durations: BTreeMap::new(),
+            decode_durations: Histogram::new(&DECODE_DURATION_BUCKETS),
+            wallpaper_apply_failures: 0,
+            last_success_times: BTreeMap::new(),
+        }
+    }
+}
+
+impl Metrics {
+    // An unchanged image counts as a successful update of the source
+    pub fn record_fetch(
+        &mut self,
+        kind: &str,
+        source: &str,
+        fetch_result: &FetchResult,
+        downloaded_bytes: u64,
+        fetch_duration: Duration,
+    ) {
+        let source_labels = (kind.to_string(), source.to_string());
+
+        *self.fetch_attempts.entry(source_labels.clone()).or_default() += 1;
+        *self.downloaded_bytes.entry(source_labels.clone()).or_default() += downloaded_bytes;
+        self.fetch_durations
+            .entry(source_labels.clone())
+            .or_insert_with(|| Histogram::new(&FETCH_DURATION_BUCKETS))
+            .observe(fetch_duration.as_secs_f64());
+
+        match fetch_result {
+            FetchResult::Succeeded => {
+                *self.fetch_successes.entry(source_labels.clone()).or_default() += 1;
+                self.last_success_times.insert(source_labels, SystemTime::now());
+            }
+            FetchResult::NotModified => {
+                *self.fetch_not_modified.entry(source_labels.clone()).or_default() += 1;
+                self.last_success_times.insert(source_labels, SystemTime::now());
+            }
+            FetchResult::Failed(fetch_error) => {
+                *self
+                    .fetch_failures
+                    .entry((source_labels, fetch_error.reason()))
+                    .or_default() += 1;
+            }
+            FetchResult::NotAttempted => {}
+        }
+    }
+
+    pub fn record_decode(&mut self, decode_duration: Duration) {
+        self.decode_durations.observe(decode_duration.as_secs_f64());
+    }
+
+    pub fn record_wallpaper_apply_failure(&mut self) {
+        self.wallpaper_apply_failures += 1;
+    }
+
+    // Prometheus text exposition format
+    pub fn to_exposition_format(&self) -> String {
+        let mut exposition = String::new();
+
+        write_counters(
+            &mut exposition,
+            "pics2wall_fetch_attempts_total",
+            "Fetches started",
+            &self.fetch_attempts,
+        );
+        write_counters(
+            &mut exposition,
+            "pics2wall_fetch_successes_total",
+            "Fetches that stored a new wallpaper image",
+            &self.fetch_successes,
+        );
+        write_counters(
+            &mut exposition,
+            "pics2wall_fetch_not_modified_total",
+            "Fetches that found the image unchanged",
+            &self.fetch_not_modified,
+        );
+
+        write_header(
+            &mut exposition,
+            "pics2wall_fetch_failures_total",
+            "counter",
+            "Failed fetches by reason",
+        );
+        for ((source_labels, reason), count) in &self.fetch_failures {
+            let _ = writeln!(
+                exposition,
+                "pics2wall_fetch_failures_total{{{},reason=\"{}\"}} {}",
+                format_source_labels(source_labels),
+                reason,
+                count
+            );
+        }
+
+        write_counters(
+            &mut exposition,
+            "pics2wall_downloaded_bytes_total",
+            "Bytes of fetched images before decoding",
+            &self.downloaded_bytes,
+        );
+
+        write_header(
+            &mut exposition,
+            "pics2wall_fetch_duration_seconds",
+            "histogram",
+            "Duration of fetches including decoding and storing the image",
+        );
+        for (source_labels, histogram) in &self.fetch_durations {
+            histogram.write_to(
+                &mut exposition,
+                "pics2wall_fetch_duration_seconds",
+                &format_source_labels(source_labels),
+            );
+        }
+
+        write_header(
+            &mut exposition,
+            "pics2wall_decode_duration_seconds",
+            "histogram",
+            "Duration of decoding and validating fetched images",
+        );
+        self.decode_durations
+            .write_to(&mut exposition, "pics2wall_decode_duration_seconds", "");
+
+        write_header(
+            &mut exposition,
+            "pics2wall_wallpaper_apply_failures_total",
+            "counter",
+            "Failures setting the stored image as wallpaper",
+        );
+        let _ = writeln!(
+            exposition,
+            "pics2wall_wallpaper_apply_failures_total {}",
+            self.wallpaper_apply_failures
+        );
+
+        write_header(
+            &mut exposition,
+            "pics2wall_last_success_timestamp_seconds",
+            "gauge",
+            "Unix time of the last successful update of the source",
+        );
+        for (source_labels, last_success_time) in &self.last_success_times {
+            let _ = writeln!(
+                exposition,
+                "pics2wall_last_success_timestamp_seconds{{{}}} {}",
+                format_source_labels(source_labels),
+                last_success_time
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+            );
+        }
+
+        write_header(
+            &mut exposition,
+            "pics2wall_seconds_since_last_success",
+            "gauge",
+            "Seconds since the last successful update of the source",
+        );
+        for (source_labels, last_success_time) in &self.last_success_times {
+            let _ = writeln!(
+                exposition,
+                "pics2wall_seconds_since_last_success{{{}}} {}",
+                format_source_labels(source_labels),
+                last_success_time.elapsed().unwrap_or_default().as_secs()
+            );
+        }
+
+        exposition
+    }
+}
+
+// Called on every update tick, so the ages in the file stay current
+pub fn write_metrics_textfile(my_app: &MyApp) {
+    let textfile = my_app.config.metrics.lock().unwrap().textfile.clone();
+
+    if textfile.is_empty() {
+        return;
+    }
+
+    let exposition = my_app.metrics.lock().unwrap().to_exposition_format();
+
+    match write_file_atomically(Path::new(&textfile), exposition.as_bytes()) {
+        Ok(_) => {}
+        Err(e) => log::error!("Metrics file {} could not be written - {}", textfile, e),
+    }
+}
+
+fn write_header(exposition: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(exposition, "# HELP {} {}", name, help);
+    let _ = writeln!(exposition, "# TYPE {} {}", name, metric_type);
+}
+
+fn write_counters(
+    exposition: &mut String,
+    name: &str,
+    help: &str,
+    counters: &BTreeMap<SourceLabels, u64>,
+) {
+    write_header(exposition, name, "counter", help);
+
+    for (source_labels, count) in counters {
+        let _ = writeln!(
+            exposition,
+            "{}{{{}}} {}",
+            name,
+            format_source_labels(source_labels),
+            count
+        );
+    }
+}
+
+fn format_source_labels((kind, source): &SourceLabels) -> String {
+    format!(
+        "kind=\"{}\",source=\"{}\"",
+        escape_label_value(kind),
+        escape_label_value(source)
+    )
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn braced(labels: &str) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels)
+    }
+}
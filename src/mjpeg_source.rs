@@ -0,0 +1,381 @@
+use std::cell::RefCell;
+
+use chrono::Local;
+use egui::Ui;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    app_gui::{ui_add_config_label, MyApp},
+    config_validation::{validate_url_address, ValidationIssue},
+    download_utils::{follow_redirects, new_fetch_handle, redirect_url, Hop},
+    fetch_result::{FetchError, ImageMetadata},
+    image_source::{FetchContext, FetchedImage, ImageSource},
+};
+
+// Settings of multipart/x-mixed-replace MJPEG streams. Cameras often send a stale or
+// half-exposed first frame, skip_frames drops that many complete frames before capturing.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct MjpegSource {
+    pub skip_frames: u32,
+}
+
+// Captures a single frame of the MJPEG stream at the image address
+pub struct Mjpeg;
+
+impl ImageSource for Mjpeg {
+    fn kind(&self) -> &'static str {
+        "Mjpeg"
+    }
+
+    fn address_label(&self) -> &'static str {
+        "Stream address:"
+    }
+
+    fn fetch(&self, context: &FetchContext) -> Result<Option<FetchedImage>, FetchError> {
+        Ok(Some(FetchedImage {
+            data: download_frame(context)?,
+            metadata: ImageMetadata {
+                capture_time: Some(Local::now()),
+                ..ImageMetadata::default()
+            },
+        }))
+    }
+
+    fn validate(&self, config: &AppConfig, issues: &mut Vec<ValidationIssue>) {
+        validate_url_address(&config.image_address.lock().unwrap(), false, issues);
+    }
+
+    fn ui_add_settings(&self, ui: &mut Ui, my_app: &MyApp, _desired_width: f32) {
+        let mjpeg_handle = &mut *my_app.config.mjpeg.lock().unwrap();
+
+        let is_overridden = ui_add_config_label(ui, my_app, "Skip frames:", "mjpeg.skip_frames");
+        ui.add_enabled(
+            !is_overridden,
+            egui::DragValue::new(&mut mjpeg_handle.skip_frames).clamp_range(0..=100),
+        )
+        .on_hover_text("Complete frames dropped before the captured one");
+
+        ui.end_row();
+    }
+}
+
+fn download_frame(context: &FetchContext) -> Result<Vec<u8>, FetchError> {
+    follow_redirects(&context.source_address, |stream_url| request_frame(context, stream_url))
+}
+
+// Reads the stream until one complete frame arrived and closes the connection
+fn request_frame(context: &FetchContext, stream_url: &str) -> Result<Hop<Vec<u8>>, FetchError> {
+    let (mut easy, download_limits) = new_fetch_handle(context, stream_url, &[])?;
+    let skip_frames = context.config.mjpeg.lock().unwrap().skip_frames;
+
+    // the stream never ends by itself, a camera trickling data without completing a frame
+    // would keep the fetch open
+    match easy.timeout(AppConstants::MJPEG_FRAME_TIMEOUT) {
+        Ok(_) => {}
+        Err(_) => log::error!("Setting the frame timeout for the stream failed"),
+    }
+
+    let content_type = RefCell::new(String::new());
+    let mut frame_reader: Option<Result<MjpegFrameReader, String>> = None;
+    let mut received_size: u64 = 0;
+    let mut is_download_too_large = false;
+    let transfer_result = {
+        let mut transfer = easy.transfer();
+        transfer
+            .header_function(|header| {
+                if let Some((name, value)) = String::from_utf8_lossy(header).split_once(':') {
+                    if name.trim().eq_ignore_ascii_case("content-type") {
+                        *content_type.borrow_mut() = value.trim().to_string();
+                    }
+                }
+                true
+            })
+            .expect("Setting header function for transfer failed");
+        transfer
+            .write_function(|data| {
+                received_size += data.len() as u64;
+                if received_size > download_limits.max_download_size {
+                    is_download_too_large = true;
+                    return Ok(0);
+                }
+
+                let frame_reader = frame_reader.get_or_insert_with(|| {
+                    MjpegFrameReader::new(&content_type.borrow(), skip_frames)
+                });
+
+                // returning fewer bytes than received closes the stream
+                match frame_reader {
+                    Ok(frame_reader) => {
+                        if frame_reader.push(data) {
+                            Ok(0)
+                        } else {
+                            Ok(data.len())
+                        }
+                    }
+                    Err(_) => Ok(0),
+                }
+            })
+            .expect("Setting write function for transfer failed");
+
+        transfer.perform()
+    };
+
+    // the redirect body is no stream, reading it ends with an aborted transfer
+    if let Some(redirect_url) = redirect_url(&mut easy) {
+        return Ok(Hop::Redirect(redirect_url));
+    }
+
+    if let Some(Ok(frame_reader)) = &mut frame_reader {
+        if let Some(frame) = frame_reader.take_frame() {
+            return Ok(Hop::Response(frame));
+        }
+    }
+
+    match easy.response_code() {
+        Ok(status_code) if status_code >= 400 => return Err(FetchError::HttpStatus(status_code)),
+        _ => {}
+    }
+
+    if is_download_too_large {
+        return Err(FetchError::TooLarge(download_limits.max_download_size));
+    }
+
+    if let Some(Err(_)) = frame_reader {
+        let content_type = content_type.into_inner();
+        return Err(FetchError::UnexpectedContentType(if content_type.is_empty() {
+            String::from("(none)")
+        } else {
+            content_type
+        }));
+    }
+
+    match transfer_result {
+        Err(e) if e.is_operation_timedout() => Err(FetchError::Transfer(format!(
+            "no complete frame received within {} seconds",
+            AppConstants::MJPEG_FRAME_TIMEOUT.as_secs()
+        ))),
+        Err(e) => Err(FetchError::Transfer(e.to_string())),
+        Ok(_) => Err(FetchError::Transfer(String::from(
+            "the stream ended before a complete frame was received",
+        ))),
+    }
+}
+
+// Collects stream data until one complete frame (after the skipped ones) is available
+pub struct MjpegFrameReader {
+    boundary: String,
+    delimiter: Option<Vec<u8>>,
+    buffer: Vec<u8>,
+    frames_to_skip: u32,
+    frame: Option<Vec<u8>>,
+}
+
+impl MjpegFrameReader {
+    pub fn new(content_type: &str, skip_frames: u32) -> Result<Self, String> {
+        let (media_type, parameters) = content_type.split_once(';').unwrap_or((content_type, ""));
+
+        if !media_type.trim().eq_ignore_ascii_case("multipart/x-mixed-replace") {
+            return Err(format!("{} is not an MJPEG stream", media_type.trim()));
+        }
+
+        let boundary = parameters
+            .split(';')
+            .filter_map(|parameter| parameter.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("boundary"))
+            .map(|(_, value)| value.trim().trim_matches('"').to_string())
+            .filter(|boundary| !boundary.is_empty())
+            .ok_or_else(|| String::from("the stream content type has no boundary"))?;
+
+        Ok(Self {
+            boundary,
+            delimiter: None,
+            buffer: Vec::new(),
+            frames_to_skip: skip_frames,
+            frame: None,
+        })
+    }
+
+    // Returns true once the frame is complete and the connection can be closed
+    pub fn push(&mut self, data: &[u8]) -> bool {
+        self.buffer.extend_from_slice(data);
+
+        while self.frame.is_none() {
+            match self.take_next_part() {
+                Some(_) if self.frames_to_skip > 0 => self.frames_to_skip -= 1,
+                Some(part_body) => self.frame = Some(part_body),
+                None => break,
+            }
+        }
+
+        self.frame.is_some()
+    }
+
+    pub fn take_frame(&mut self) -> Option<Vec<u8>> {
+        self.frame.take()
+    }
+
+    fn take_next_part(&mut self) -> Option<Vec<u8>> {
+        let delimiter = self.find_delimiter()?;
+        let part_start = find_bytes(&self.buffer, &delimiter, 0)? + delimiter.len();
+        let headers_end = find_bytes(&self.buffer, b"\r\n\r\n", part_start)?;
+        let body_start = headers_end + 4;
+
+        let content_length = String::from_utf8_lossy(&self.buffer[part_start..headers_end])
+            .lines()
+            .filter_map(|header| header.split_once(':'))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+
+        // without a Content-Length the body ends at the next delimiter
+        let body_end = match content_length {
+            Some(content_length) if self.buffer.len() >= body_start + content_length => {
+                body_start + content_length
+            }
+            Some(_) => return None,
+            None => {
+                let next_delimiter = find_bytes(&self.buffer, &delimiter, body_start)?;
+                let mut body_end = next_delimiter;
+                while body_end > body_start && matches!(self.buffer[body_end - 1], b'\r' | b'\n') {
+                    body_end -= 1;
+                }
+                body_end
+            }
+        };
+
+        let part_body = self.buffer[body_start..body_end].to_vec();
+        self.buffer.drain(..body_end);
+
+        Some(part_body)
+    }
+
+    // Servers disagree on whether the boundary parameter already contains the leading "--"
+    fn find_delimiter(&mut self) -> Option<Vec<u8>> {
+        if self.delimiter.is_none() {
+            let prefixed_delimiter = format!("--{}", self.boundary).into_bytes();
+            let bare_delimiter = self.boundary.clone().into_bytes();
+
+            self.delimiter = if find_bytes(&self.buffer, &prefixed_delimiter, 0).is_some() {
+                Some(prefixed_delimiter)
+            } else if find_bytes(&self.buffer, &bare_delimiter, 0).is_some() {
+                Some(bare_delimiter)
+            } else {
+                None
+            };
+        }
+
+        self.delimiter.clone()
+    }
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|position| position + from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MjpegFrameReader;
+
+    const CONTENT_TYPE: &str = "multipart/x-mixed-replace; boundary=frame";
+
+    fn part(body: &[u8], with_content_length: bool) -> Vec<u8> {
+        let mut part = b"--frame\r\nContent-Type: image/jpeg\r\n".to_vec();
+        if with_content_length {
+            part.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+        }
+        part.extend_from_slice(b"\r\n");
+        part.extend_from_slice(body);
+        part.extend_from_slice(b"\r\n");
+        part
+    }
+
+    fn stream(bodies: &[&[u8]], with_content_length: bool) -> Vec<u8> {
+        bodies
+            .iter()
+            .flat_map(|body| part(body, with_content_length))
+            .collect()
+    }
+
+    #[test]
+    fn reads_a_frame_with_content_length() {
+        let mut frame_reader = MjpegFrameReader::new(CONTENT_TYPE, 0).unwrap();
+
+        assert!(frame_reader.push(&stream(&[b"first\r\n\r\n--frame"], true)));
+        assert_eq!(
+            frame_reader.take_frame(),
+            Some(b"first\r\n\r\n--frame".to_vec())
+        );
+    }
+
+    #[test]
+    fn waits_for_the_next_delimiter_without_content_length() {
+        let mut frame_reader = MjpegFrameReader::new(CONTENT_TYPE, 0).unwrap();
+
+        assert!(!frame_reader.push(&part(b"first", false)));
+        assert!(frame_reader.push(b"--frame\r\n"));
+        assert_eq!(frame_reader.take_frame(), Some(b"first".to_vec()));
+    }
+
+    #[test]
+    fn reads_frames_split_across_chunks() {
+        for with_content_length in [true, false] {
+            let data = stream(&[b"first", b"second"], with_content_length);
+
+            // every split point, including inside the delimiter and the header end
+            for split in 1..data.len() {
+                let mut frame_reader = MjpegFrameReader::new(CONTENT_TYPE, 0).unwrap();
+                let is_complete =
+                    frame_reader.push(&data[..split]) || frame_reader.push(&data[split..]);
+
+                assert!(is_complete, "split at {}", split);
+                assert_eq!(frame_reader.take_frame(), Some(b"first".to_vec()));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_frames_pushed_byte_by_byte() {
+        let data = stream(&[b"first", b"second", b"third"], true);
+        let mut frame_reader = MjpegFrameReader::new(CONTENT_TYPE, 2).unwrap();
+
+        let complete_position = data
+            .iter()
+            .position(|byte| frame_reader.push(std::slice::from_ref(byte)));
+
+        assert_eq!(complete_position, Some(data.len() - 3));
+        assert_eq!(frame_reader.take_frame(), Some(b"third".to_vec()));
+    }
+
+    #[test]
+    fn skips_frames() {
+        let mut frame_reader = MjpegFrameReader::new(CONTENT_TYPE, 1).unwrap();
+
+        assert!(frame_reader.push(&stream(&[b"stale", b"fresh"], true)));
+        assert_eq!(frame_reader.take_frame(), Some(b"fresh".to_vec()));
+    }
+
+    #[test]
+    fn accepts_boundaries_with_leading_dashes() {
+        let mut frame_reader = MjpegFrameReader::new(
+            "Multipart/X-Mixed-Replace; charset=binary; boundary=\"--frame\"",
+            0,
+        )
+        .unwrap();
+
+        assert!(frame_reader.push(&stream(&[b"first"], true)));
+        assert_eq!(frame_reader.take_frame(), Some(b"first".to_vec()));
+    }
+
+    #[test]
+    fn rejects_other_content_types() {
+        assert!(MjpegFrameReader::new("image/jpeg", 0).is_err());
+        assert!(MjpegFrameReader::new("multipart/x-mixed-replace", 0).is_err());
+        assert!(MjpegFrameReader::new("multipart/x-mixed-replace; boundary=", 0).is_err());
+    }
+}
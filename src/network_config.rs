@@ -0,0 +1,169 @@
+use std::{collections::BTreeMap, fmt};
+
+use curl::easy::{Easy, IpResolve};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    secrets_utils::{is_secret_reference, resolve_secret_reference},
+    source_auth::{mask_secret, mask_url_credentials},
+};
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize, Default)]
+pub enum IpVersion {
+    #[default]
+    Any,
+    V4,
+    V6,
+}
+
+// Network settings of the fetch handle. The proxy URL scheme selects the proxy type
+// (http://, https://, socks5://, socks5h://), an empty proxy falls back to the
+// HTTP_PROXY / HTTPS_PROXY / ALL_PROXY / NO_PROXY environment variables unless disabled.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    pub proxy: String,
+    pub proxy_username: String,
+    pub proxy_password: String,
+    pub no_proxy: String,
+    pub use_environment_proxy: bool,
+    pub ca_bundle: String,
+    pub client_certificate: String,
+    pub client_key: String,
+    pub client_key_password: String,
+    pub ip_version: IpVersion,
+    pub user_agent: String,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            proxy: String::from(""),
+            proxy_username: String::from(""),
+            proxy_password: String::from(""),
+            no_proxy: String::from(""),
+            use_environment_proxy: true,
+            ca_bundle: String::from(""),
+            client_certificate: String::from(""),
+            client_key: String::from(""),
+            client_key_password: String::from(""),
+            ip_version: IpVersion::Any,
+            user_agent: String::from(""),
+        }
+    }
+}
+
+impl fmt::Debug for NetworkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NetworkConfig")
+            .field("proxy", &mask_url_credentials(&self.proxy))
+            .field("proxy_username", &self.proxy_username)
+            .field("proxy_password", &mask_secret(&self.proxy_password))
+            .field("no_proxy", &self.no_proxy)
+            .field("use_environment_proxy", &self.use_environment_proxy)
+            .field("ca_bundle", &self.ca_bundle)
+            .field("client_certificate", &self.client_certificate)
+            .field("client_key", &self.client_key)
+            .field("client_key_password", &mask_secret(&self.client_key_password))
+            .field("ip_version", &self.ip_version)
+            .field("user_agent", &self.user_agent)
+            .finish()
+    }
+}
+
+impl NetworkConfig {
+    // Expects a config with resolved secrets, see resolve_secrets()
+    pub fn apply_to_handle(&self, easy: &mut Easy, url: &str) -> Result<(), String> {
+        let (proxy, no_proxy) = self.effective_proxy(url);
+        // an empty proxy string explicitly disables libcurl's own environment lookup
+        easy.proxy(&proxy).map_err(|e| e.to_string())?;
+        easy.noproxy(&no_proxy).map_err(|e| e.to_string())?;
+
+        if !self.proxy_username.is_empty() {
+            easy.proxy_username(&self.proxy_username)
+                .map_err(|e| e.to_string())?;
+            easy.proxy_password(&self.proxy_password)
+                .map_err(|e| e.to_string())?;
+        }
+
+        if !self.ca_bundle.is_empty() {
+            easy.cainfo(&self.ca_bundle).map_err(|e| e.to_string())?;
+        }
+
+        if !self.client_certificate.is_empty() {
+            easy.ssl_cert(&self.client_certificate)
+                .map_err(|e| e.to_string())?;
+        }
+
+        if !self.client_key.is_empty() {
+            easy.ssl_key(&self.client_key).map_err(|e| e.to_string())?;
+        }
+
+        if !self.client_key_password.is_empty() {
+            easy.key_password(&self.client_key_password)
+                .map_err(|e| e.to_string())?;
+        }
+
+        easy.ip_resolve(match self.ip_version {
+            IpVersion::Any => IpResolve::Any,
+            IpVersion::V4 => IpResolve::V4,
+            IpVersion::V6 => IpResolve::V6,
+        })
+        .map_err(|e| e.to_string())?;
+
+        if !self.user_agent.is_empty() {
+            easy.useragent(&self.user_agent)
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    // Returns the proxy and no-proxy list to use for the URL, the configured proxy wins over
+    // the environment
+    pub fn effective_proxy(&self, url: &str) -> (String, String) {
+        if !self.proxy.is_empty() || !self.use_environment_proxy {
+            return (self.proxy.clone(), self.no_proxy.clone());
+        }
+
+        let proxy_variable_names: &[&str] = if url.to_ascii_lowercase().starts_with("https://") {
+            &["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]
+        } else {
+            &["HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]
+        };
+
+        let proxy = first_environment_variable(proxy_variable_names);
+        let no_proxy = if self.no_proxy.is_empty() {
+            first_environment_variable(&["NO_PROXY", "no_proxy"])
+        } else {
+            self.no_proxy.clone()
+        };
+
+        (proxy, no_proxy)
+    }
+
+    pub fn has_secret_references(&self) -> bool {
+        is_secret_reference(&self.proxy_password) || is_secret_reference(&self.client_key_password)
+    }
+
+    pub fn resolve_secrets(
+        &self,
+        secrets: &BTreeMap<String, String>,
+    ) -> Result<NetworkConfig, String> {
+        let mut resolved_network_config = self.clone();
+        resolved_network_config.proxy_password =
+            resolve_secret_reference(&self.proxy_password, secrets)?;
+        resolved_network_config.client_key_password =
+            resolve_secret_reference(&self.client_key_password, secrets)?;
+
+        Ok(resolved_network_config)
+    }
+}
+
+fn first_environment_variable(names: &[&str]) -> String {
+    names
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default()
+}
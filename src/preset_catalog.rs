@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    app_config::AppConfig,
+    app_constants::AppConstants,
+    config_overrides::ConfigOverrides,
+    duration_utils::{format_duration, parse_duration},
+    source_auth::SourceAuth,
+};
+
+// Catalogs written for a newer version of the app are rejected instead of half-applied
+pub const PRESET_CATALOG_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PresetCatalog {
+    pub version: u32,
+    pub presets: Vec<Preset>,
+}
+
+// A ready-made source. The source object holds config keys (kind, image_address and the
+// source's own section, e.g. json_api) replacing the current values when the preset is used.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub location: String,
+    pub source: Map<String, Value>,
+    #[serde(default)]
+    pub recommended_interval: String,
+    #[serde(default)]
+    pub timezone: String,
+    #[serde(default)]
+    pub credit: String,
+    // the catalog the preset was loaded from, shown in the GUI
+    #[serde(skip)]
+    pub catalog: String,
+}
+
+impl Preset {
+    pub fn kind(&self) -> &str {
+        self.source
+            .get("kind")
+            .and_then(|kind| kind.as_str())
+            .unwrap_or("Http")
+    }
+
+    pub fn matches(&self, search_string: &str) -> bool {
+        let search_string = search_string.trim().to_lowercase();
+
+        [&self.name, &self.location, &self.credit, &self.timezone]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&search_string))
+            || self.kind().to_lowercase().contains(&search_string)
+    }
+
+    // Replaces the source settings of the config. The authentication of the previous source is
+    // reset so its credentials are never sent to the preset's host, overridden keys keep their
+    // overridden values.
+    pub fn apply_to(
+        &self,
+        config: &AppConfig,
+        config_overrides: &ConfigOverrides,
+    ) -> Result<(), String> {
+        let mut config_value = serde_json::to_value(config).map_err(|e| e.to_string())?;
+        let config_object = config_value.as_object_mut().unwrap();
+
+        config_object.insert(
+            String::from("source_auth"),
+            serde_json::to_value(SourceAuth::default()).unwrap(),
+        );
+
+        for (key, value) in &self.source {
+            if !config_object.contains_key(key) {
+                return Err(format!("Unknown config key {} in preset {}", key, self.name));
+            }
+            config_object.insert(key.clone(), value.clone());
+        }
+
+        if !self.recommended_interval.is_empty() {
+            let recommended_interval = parse_duration(&self.recommended_interval)
+                .map_err(|e| {
+                    format!("Invalid recommended interval of preset {} - {}", self.name, e)
+                })?
+                .max(AppConstants::AUTO_UPDATE_MIN_INTERVAL);
+            config_object.insert(
+                String::from("auto_update_interval"),
+                Value::String(format_duration(recommended_interval)),
+            );
+        }
+
+        let preset_config: AppConfig = serde_json::from_value(config_value)
+            .map_err(|e| format!("Invalid source of preset {} - {}", self.name, e))?;
+
+        config.apply(&config_overrides.apply(&preset_config)?);
+
+        log::info!("Preset {} applied to the configuration", self.name);
+
+        Ok(())
+    }
+}
+
+pub fn load_preset_catalog(path: &Path) -> Result<PresetCatalog, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Preset catalog {} could not be read - {}", path.display(), e))?;
+
+    let mut preset_catalog: PresetCatalog = serde_json::from_str(&content)
+        .map_err(|e| format!("Preset catalog {} is invalid - {}", path.display(), e))?;
+
+    if preset_catalog.version > PRESET_CATALOG_VERSION {
+        return Err(format!(
+            "Preset catalog {} has version {}, this app supports up to version {}",
+            path.display(),
+            preset_catalog.version,
+            PRESET_CATALOG_VERSION
+        ));
+    }
+
+    for preset in &mut preset_catalog.presets {
+        preset.catalog = path.display().to_string();
+    }
+
+    Ok(preset_catalog)
+}
+
+// Loads the built-in catalog followed by the local catalog files of the config. Catalogs that
+// cannot be loaded are skipped and reported.
+pub fn load_preset_catalogs(config: &AppConfig) -> (Vec<Preset>, Vec<String>) {
+    let mut presets = Vec::new();
+    let mut errors = Vec::new();
+
+    let catalog_locations =
+        std::iter::once(AppConstants::PRESET_CATALOG_FILE_LOCATION.to_string())
+            .chain(config.preset_catalogs.lock().unwrap().clone());
+
+    for catalog_location in catalog_locations {
+        match load_preset_catalog(Path::new(&catalog_location)) {
+            Ok(preset_catalog) => presets.extend(preset_catalog.presets),
+            Err(e) => {
+                log::error!("{}", e);
+                errors.push(e);
+            }
+        }
+    }
+
+    (presets, errors)
+}
@@ -0,0 +1,232 @@
+use std::{collections::BTreeMap, path::Path};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    XChaCha20Poly1305, XNonce,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{app_constants::AppConstants, common_utils::write_file_atomically};
+
+pub const SECRET_REFERENCE_PREFIX: &str = "secret:";
+
+// The whole name -> value map is encrypted as one blob, so not even secret names are readable
+// without the passphrase / key file. A fresh salt and nonce are generated on every save.
+#[derive(Serialize, Deserialize)]
+struct EncryptedSecretsFile {
+    version: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+pub fn is_secret_reference(value: &str) -> bool {
+    value.starts_with(SECRET_REFERENCE_PREFIX)
+}
+
+// The passphrase environment variable takes precedence over the configured key file
+pub fn read_key_material(key_file_location: &str) -> Result<Vec<u8>, String> {
+    if let Ok(passphrase) = std::env::var(AppConstants::SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE) {
+        if !passphrase.is_empty() {
+            return Ok(passphrase.into_bytes());
+        }
+    }
+
+    if !key_file_location.is_empty() {
+        return std::fs::read(key_file_location)
+            .map_err(|e| format!("Secrets key file {} could not be read ({})", key_file_location, e));
+    }
+
+    Err(format!(
+        "No secrets passphrase available, set {} or configure a secrets key file",
+        AppConstants::SECRETS_PASSPHRASE_ENVIRONMENT_VARIABLE
+    ))
+}
+
+pub fn load_secrets(key_material: &[u8]) -> Result<BTreeMap<String, String>, String> {
+    if !Path::new(AppConstants::SECRETS_FILE_LOCATION).exists() {
+        return Ok(BTreeMap::new());
+    }
+
+    let serialized_secrets_file = std::fs::read_to_string(AppConstants::SECRETS_FILE_LOCATION)
+        .map_err(|e| format!("Secrets file could not be read ({})", e))?;
+
+    decrypt_secrets(&serialized_secrets_file, key_material)
+}
+
+pub fn save_secrets(secrets: &BTreeMap<String, String>, key_material: &[u8]) -> Result<(), String> {
+    write_file_atomically(
+        Path::new(AppConstants::SECRETS_FILE_LOCATION),
+        encrypt_secrets(secrets, key_material)?.as_bytes(),
+    )
+    .map_err(|e| format!("Secrets file could not be written ({})", e))
+}
+
+fn decrypt_secrets(
+    serialized_secrets_file: &str,
+    key_material: &[u8],
+) -> Result<BTreeMap<String, String>, String> {
+    let secrets_file: EncryptedSecretsFile = serde_json::from_str(serialized_secrets_file)
+        .map_err(|e| format!("Secrets file is corrupted ({})", e))?;
+
+    let salt = base64::decode(&secrets_file.salt)
+        .map_err(|_| String::from("Secrets file is corrupted (salt)"))?;
+    let nonce = base64::decode(&secrets_file.nonce)
+        .map_err(|_| String::from("Secrets file is corrupted (nonce)"))?;
+    let ciphertext = base64::decode(&secrets_file.ciphertext)
+        .map_err(|_| String::from("Secrets file is corrupted (ciphertext)"))?;
+
+    if nonce.len() != 24 {
+        return Err(String::from("Secrets file is corrupted (nonce)"));
+    }
+
+    let cipher = build_cipher(key_material, &salt)?;
+    let plaintext = cipher
+        .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+        .map_err(|_| String::from("Secrets could not be decrypted, wrong passphrase or key file"))?;
+
+    serde_json::from_slice(&plaintext).map_err(|e| format!("Secrets file is corrupted ({})", e))
+}
+
+fn encrypt_secrets(
+    secrets: &BTreeMap<String, String>,
+    key_material: &[u8],
+) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    OsRng.fill_bytes(&mut salt);
+    let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+    let plaintext = serde_json::to_vec(secrets).map_err(|e| e.to_string())?;
+    let cipher = build_cipher(key_material, &salt)?;
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext.as_slice())
+        .map_err(|_| String::from("Secrets could not be encrypted"))?;
+
+    let secrets_file = EncryptedSecretsFile {
+        version: 1,
+        salt: base64::encode(salt),
+        nonce: base64::encode(nonce),
+        ciphertext: base64::encode(ciphertext),
+    };
+
+    serde_json::to_string_pretty(&secrets_file).map_err(|e| e.to_string())
+}
+
+// Values without the secret: prefix are returned unchanged
+pub fn resolve_secret_reference(
+    value: &str,
+    secrets: &BTreeMap<String, String>,
+) -> Result<String, String> {
+    match value.strip_prefix(SECRET_REFERENCE_PREFIX) {
+        Some(secret_name) => secrets
+            .get(secret_name)
+            .cloned()
+            .ok_or_else(|| format!("Secret \"{}\" not found in the secrets file", secret_name)),
+        None => Ok(value.to_string()),
+    }
+}
+
+fn build_cipher(key_material: &[u8], salt: &[u8]) -> Result<XChaCha20Poly1305, String> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(key_material, salt, &mut key)
+        .map_err(|e| format!("Secrets key derivation failed ({})", e))?;
+
+    XChaCha20Poly1305::new_from_slice(&key).map_err(|_| String::from("Invalid secrets key"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{
+        decrypt_secrets, encrypt_secrets, is_secret_reference, resolve_secret_reference,
+        EncryptedSecretsFile,
+    };
+
+    fn secrets() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (String::from("camera_password"), String::from("hunter2")),
+            (String::from("api_token"), String::from("ä€ token with spaces")),
+        ])
+    }
+
+    #[test]
+    fn decrypts_what_was_encrypted() {
+        let serialized_secrets_file = encrypt_secrets(&secrets(), b"correct horse").unwrap();
+
+        assert!(!serialized_secrets_file.contains("camera_password"));
+        assert!(!serialized_secrets_file.contains("hunter2"));
+        assert_eq!(decrypt_secrets(&serialized_secrets_file, b"correct horse"), Ok(secrets()));
+    }
+
+    #[test]
+    fn uses_a_fresh_salt_and_nonce_on_every_save() {
+        let first_file: EncryptedSecretsFile =
+            serde_json::from_str(&encrypt_secrets(&secrets(), b"correct horse").unwrap()).unwrap();
+        let second_file: EncryptedSecretsFile =
+            serde_json::from_str(&encrypt_secrets(&secrets(), b"correct horse").unwrap()).unwrap();
+
+        assert_ne!(first_file.salt, second_file.salt);
+        assert_ne!(first_file.nonce, second_file.nonce);
+        assert_ne!(first_file.ciphertext, second_file.ciphertext);
+    }
+
+    #[test]
+    fn rejects_a_wrong_passphrase() {
+        let serialized_secrets_file = encrypt_secrets(&secrets(), b"correct horse").unwrap();
+
+        let result = decrypt_secrets(&serialized_secrets_file, b"correct hors");
+
+        assert!(result.unwrap_err().contains("wrong passphrase"));
+    }
+
+    #[test]
+    fn rejects_modified_files() {
+        let serialized_secrets_file = encrypt_secrets(&secrets(), b"correct horse").unwrap();
+        let mut secrets_file: EncryptedSecretsFile =
+            serde_json::from_str(&serialized_secrets_file).unwrap();
+        let mut ciphertext = base64::decode(&secrets_file.ciphertext).unwrap();
+        ciphertext[0] ^= 1;
+        secrets_file.ciphertext = base64::encode(ciphertext);
+
+        assert!(decrypt_secrets(
+            &serde_json::to_string(&secrets_file).unwrap(),
+            b"correct horse"
+        )
+        .is_err());
+        assert!(decrypt_secrets("{}", b"correct horse")
+            .unwrap_err()
+            .contains("corrupted"));
+        assert!(decrypt_secrets(
+            &serialized_secrets_file.replace(&secrets_file.nonce, "AAAA"),
+            b"correct horse"
+        )
+        .unwrap_err()
+        .contains("corrupted"));
+    }
+
+    #[test]
+    fn resolves_secret_references() {
+        let secrets = secrets();
+
+        assert!(is_secret_reference("secret:camera_password"));
+        assert!(!is_secret_reference("camera_password"));
+        assert_eq!(
+            resolve_secret_reference("secret:camera_password", &secrets),
+            Ok(String::from("hunter2"))
+        );
+        assert_eq!(
+            resolve_secret_reference("plain value", &secrets),
+            Ok(String::from("plain value"))
+        );
+    }
+
+    #[test]
+    fn reports_missing_secrets_by_name() {
+        let result = resolve_secret_reference("secret:camera_pasword", &BTreeMap::new());
+
+        assert!(result.unwrap_err().contains("\"camera_pasword\""));
+    }
+}
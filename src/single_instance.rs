@@ -0,0 +1,150 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_constants::AppConstants,
+    app_gui::MyApp,
+    common_utils::{fetch_pic, switch_profile, switch_source},
+    windows_os_utils::{set_pic_as_wallpaper, show_app_window},
+};
+
+// Requests a second launch hands over to the running instance instead of starting another one
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum HandoffRequest {
+    ShowWindow,
+    FetchNow,
+    SwitchProfile(String),
+    SwitchSource { kind: String, image_address: String },
+}
+
+// Held for the lifetime of the process, the OS releases the lock when the process exits (also
+// after a crash), so a stale lock file never blocks the next start
+pub struct InstanceLock {
+    _lock_file: File,
+}
+
+// Returns None when another instance holds the lock
+pub fn acquire_instance_lock() -> Result<Option<InstanceLock>, String> {
+    std::fs::create_dir_all(AppConstants::RUNTIME_DIRECTORY)
+        .map_err(|e| format!("Runtime folder could not be created - {}", e))?;
+
+    // not truncated on open, a second launch must not wipe the process id of the running instance
+    let mut lock_file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(AppConstants::INSTANCE_LOCK_FILE_LOCATION)
+        .map_err(|e| format!("Instance lock file could not be opened - {}", e))?;
+
+    match lock_file.try_lock_exclusive() {
+        Ok(_) => {}
+        Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+            return Ok(None)
+        }
+        Err(e) => return Err(format!("Instance lock could not be taken - {}", e)),
+    }
+
+    // the process id only helps finding the running instance, the lock itself is what counts
+    let _ = lock_file
+        .set_len(0)
+        .and_then(|_| write!(lock_file, "{}", std::process::id()));
+
+    // requests left behind by an instance that exited before picking them up are outdated
+    let _ = take_handoff_requests();
+
+    Ok(Some(InstanceLock {
+        _lock_file: lock_file,
+    }))
+}
+
+// Requests are appended as JSON lines, the file lock keeps concurrent launches from
+// interleaving their lines with the running instance emptying the file
+pub fn send_handoff_request(request: &HandoffRequest) -> Result<(), String> {
+    let mut handoff_file = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(AppConstants::HANDOFF_FILE_LOCATION)
+        .map_err(|e| format!("Handoff file could not be opened - {}", e))?;
+
+    handoff_file
+        .lock_exclusive()
+        .map_err(|e| format!("Handoff file could not be locked - {}", e))?;
+
+    let line = serde_json::to_string(request).map_err(|e| e.to_string())?;
+    let write_result = writeln!(handoff_file, "{}", line)
+        .map_err(|e| format!("Handoff request could not be written - {}", e));
+
+    let _ = handoff_file.unlock();
+
+    write_result
+}
+
+pub fn take_handoff_requests() -> Vec<HandoffRequest> {
+    let mut handoff_file = match OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(AppConstants::HANDOFF_FILE_LOCATION)
+    {
+        Ok(handoff_file) => handoff_file,
+        Err(_) => return Vec::new(),
+    };
+
+    if let Err(e) = handoff_file.lock_exclusive() {
+        log::error!("Handoff file could not be locked - {}", e);
+        return Vec::new();
+    }
+
+    let mut content = String::new();
+    let read_result = handoff_file
+        .read_to_string(&mut content)
+        .and_then(|_| handoff_file.set_len(0));
+
+    let _ = handoff_file.unlock();
+
+    if let Err(e) = read_result {
+        log::error!("Handoff requests could not be read - {}", e);
+        return Vec::new();
+    }
+
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(request) => Some(request),
+            Err(e) => {
+                log::error!("Invalid handoff request {} ignored - {}", line, e);
+                None
+            }
+        })
+        .collect()
+}
+
+// Runs on the scheduler thread, also for the requests queued by the control API
+pub fn handle_handoff_request(my_app: &mut MyApp, request: HandoffRequest) {
+    log::info!("Handoff request {:?} picked up", request);
+
+    match request {
+        HandoffRequest::ShowWindow => show_app_window(),
+        HandoffRequest::FetchNow => {
+            if fetch_pic(my_app) {
+                set_pic_as_wallpaper(my_app);
+            }
+            *my_app.status.lock().unwrap() = String::from("Idle");
+        }
+        HandoffRequest::SwitchProfile(profile) => {
+            let _ = switch_profile(my_app, &profile);
+        }
+        HandoffRequest::SwitchSource {
+            kind,
+            image_address,
+        } => {
+            let _ = switch_source(my_app, &kind, &image_address);
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use chrono::{
+    format::{Item, StrftimeItems},
+    Local, Utc,
+};
+
+use crate::{fetch_result::ImageMetadata, windows_os_utils::get_screen_resolution};
+
+// Placeholders expanded in source addresses on every fetch, {{ and }} are literal braces
+pub const URL_TEMPLATE_PLACEHOLDERS: [&str; 6] = [
+    "{date}",
+    "{date:<strftime format>}",
+    "{utc_date:<strftime format>}",
+    "{timestamp}",
+    "{screen_width}",
+    "{screen_height}",
+];
+
+// Placeholders of caption templates, filled from the metadata of the current image. Values the
+// image does not have expand to nothing.
+pub const CAPTION_TEMPLATE_PLACEHOLDERS: [&str; 7] = [
+    "{title}",
+    "{credit}",
+    "{capture_time}",
+    "{capture_time:<strftime format>}",
+    "{camera}",
+    "{latitude}",
+    "{longitude}",
+];
+
+// Expanded values are percent-encoded, a {date:%d %B} format would otherwise put spaces into
+// the URL
+pub fn expand_url_template(template: &str) -> Result<String, String> {
+    expand_placeholders(template, |placeholder| {
+        expand_address_placeholder(placeholder).map(|value| percent_encode_value(&value))
+    })
+}
+
+// For addresses that are no URLs (local folders, commands and their arguments), the values are
+// inserted as they are
+pub fn expand_plain_template(template: &str) -> Result<String, String> {
+    expand_placeholders(template, expand_address_placeholder)
+}
+
+fn expand_address_placeholder(placeholder: &str) -> Result<String, String> {
+    Ok(match placeholder {
+        "date" => Local::now().format("%Y-%m-%d").to_string(),
+        "timestamp" => Utc::now().timestamp().to_string(),
+        "screen_width" => get_screen_resolution().0.to_string(),
+        "screen_height" => get_screen_resolution().1.to_string(),
+        _ => match placeholder.split_once(':') {
+            Some(("date", date_format)) => {
+                check_date_format(date_format)?;
+                Local::now().format(date_format).to_string()
+            }
+            Some(("utc_date", date_format)) => {
+                check_date_format(date_format)?;
+                Utc::now().format(date_format).to_string()
+            }
+            _ => return Err(unknown_placeholder(placeholder, &URL_TEMPLATE_PLACEHOLDERS)),
+        },
+    })
+}
+
+// Keeps the unreserved characters of RFC 3986 and the / and : of paths and times (e.g.
+// {date:%Y/%m/%d}), so the value fits into the path as well as the query
+fn percent_encode_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub fn validate_url_template(template: &str) -> Result<(), String> {
+    expand_placeholders(template, |placeholder| match placeholder {
+        "date" | "timestamp" | "screen_width" | "screen_height" => Ok(String::new()),
+        _ => match placeholder.split_once(':') {
+            Some(("date", date_format)) | Some(("utc_date", date_format)) => {
+                check_date_format(date_format).map(|_| String::new())
+            }
+            _ => Err(unknown_placeholder(placeholder, &URL_TEMPLATE_PLACEHOLDERS)),
+        },
+    })
+    .map(|_| ())
+}
+
+pub fn expand_caption_template(
+    template: &str,
+    image_metadata: &ImageMetadata,
+) -> Result<String, String> {
+    let gps_position = image_metadata.gps_position;
+
+    expand_placeholders(template, |placeholder| {
+        Ok(match placeholder {
+            "title" => image_metadata.title.clone(),
+            "credit" => image_metadata.credit.clone(),
+            "camera" => image_metadata.camera.clone(),
+            "capture_time" => image_metadata
+                .capture_time
+                .map(|capture_time| capture_time.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default(),
+            "latitude" => gps_position
+                .map(|(latitude, _)| format!("{:.5}", latitude))
+                .unwrap_or_default(),
+            "longitude" => gps_position
+                .map(|(_, longitude)| format!("{:.5}", longitude))
+                .unwrap_or_default(),
+            _ => match placeholder.split_once(':') {
+                Some(("capture_time", date_format)) => {
+                    check_date_format(date_format)?;
+                    image_metadata
+                        .capture_time
+                        .map(|capture_time| capture_time.format(date_format).to_string())
+                        .unwrap_or_default()
+                }
+                _ => {
+                    return Err(unknown_placeholder(
+                        placeholder,
+                        &CAPTION_TEMPLATE_PLACEHOLDERS,
+                    ))
+                }
+            },
+        })
+    })
+}
+
+pub fn validate_caption_template(template: &str) -> Result<(), String> {
+    expand_caption_template(template, &ImageMetadata::default()).map(|_| ())
+}
+
+fn expand_placeholders(
+    template: &str,
+    mut expand_placeholder: impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut expanded = String::new();
+    let mut rest = template;
+
+    while let Some(brace_position) = rest.find(['{', '}']) {
+        expanded.push_str(&rest[..brace_position]);
+        let from_brace = &rest[brace_position..];
+
+        if from_brace.starts_with("{{") || from_brace.starts_with("}}") {
+            expanded.push_str(&from_brace[..1]);
+            rest = &from_brace[2..];
+        } else if from_brace.starts_with('}') {
+            return Err(String::from(
+                "Unmatched } in the template, use }} for a literal brace",
+            ));
+        } else {
+            let placeholder_end = from_brace.find('}').ok_or_else(|| {
+                String::from("Unclosed { in the template, use {{ for a literal brace")
+            })?;
+            expanded.push_str(&expand_placeholder(&from_brace[1..placeholder_end])?);
+            rest = &from_brace[placeholder_end + 1..];
+        }
+    }
+
+    expanded.push_str(rest);
+
+    Ok(expanded)
+}
+
+// chrono panics when formatting with an invalid specifier, so formats are checked first
+fn check_date_format(date_format: &str) -> Result<(), String> {
+    if StrftimeItems::new(date_format).any(|item| item == Item::Error) {
+        Err(format!("Invalid date format \"{}\"", date_format))
+    } else {
+        Ok(())
+    }
+}
+
+fn unknown_placeholder(placeholder: &str, supported_placeholders: &[&str]) -> String {
+    format!(
+        "Unknown placeholder {{{}}}, supported: {}",
+        placeholder,
+        supported_placeholders.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use crate::fetch_result::ImageMetadata;
+
+    use super::{
+        expand_caption_template, expand_placeholders, expand_plain_template, expand_url_template,
+        percent_encode_value, validate_url_template,
+    };
+
+    fn expand_upper(template: &str) -> Result<String, String> {
+        expand_placeholders(template, |placeholder| Ok(placeholder.to_uppercase()))
+    }
+
+    #[test]
+    fn expands_placeholders_anywhere() {
+        assert_eq!(expand_upper("plain text"), Ok(String::from("plain text")));
+        assert_eq!(expand_upper("{a}"), Ok(String::from("A")));
+        assert_eq!(expand_upper("x{a}y{b}z"), Ok(String::from("xAyBz")));
+        assert_eq!(expand_upper("{a}{b}"), Ok(String::from("AB")));
+        assert_eq!(expand_upper("{date:%Y}"), Ok(String::from("DATE:%Y")));
+        assert_eq!(expand_upper("{}"), Ok(String::from("")));
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(expand_upper("{{a}}"), Ok(String::from("{a}")));
+        assert_eq!(expand_upper("{{{a}}}"), Ok(String::from("{A}")));
+        assert_eq!(expand_upper("}}{{"), Ok(String::from("}{")));
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces() {
+        assert!(expand_upper("{a").is_err());
+        assert!(expand_upper("a}").is_err());
+        assert!(expand_upper("{a}}").is_err());
+        assert!(expand_upper("{{a}").is_err());
+    }
+
+    #[test]
+    fn stops_at_the_first_failing_placeholder() {
+        let mut expanded_placeholders = Vec::new();
+        let result = expand_placeholders("{a}{b}{c}", |placeholder| {
+            expanded_placeholders.push(placeholder.to_string());
+            if placeholder == "b" {
+                Err(String::from("failed"))
+            } else {
+                Ok(String::new())
+            }
+        });
+
+        assert_eq!(result, Err(String::from("failed")));
+        assert_eq!(expanded_placeholders, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn percent_encodes_url_values() {
+        assert_eq!(percent_encode_value("2024-05-01"), "2024-05-01");
+        assert_eq!(
+            percent_encode_value("2024/05/01 12:00"),
+            "2024/05/01%2012:00"
+        );
+        assert_eq!(percent_encode_value("a&b=c?d#e"), "a%26b%3Dc%3Fd%23e");
+        assert_eq!(percent_encode_value("100%+~_."), "100%25%2B~_.");
+        assert_eq!(percent_encode_value("März"), "M%C3%A4rz");
+    }
+
+    #[test]
+    fn encodes_values_only_in_urls() {
+        let url = expand_url_template("https://example.com/{date:%d %B}?q={{x}}").unwrap();
+        assert!(!url.contains(' '));
+        assert!(url.starts_with("https://example.com/"));
+        assert!(url.ends_with("?q={x}"));
+
+        let path = expand_plain_template("C:\\Pictures\\{date:%d %B}").unwrap();
+        assert!(path.contains(' '));
+    }
+
+    #[test]
+    fn validates_url_templates() {
+        assert!(validate_url_template("https://example.com/{date}/{timestamp}").is_ok());
+        assert!(validate_url_template("https://example.com/{utc_date:%Y%m%d}").is_ok());
+        assert!(validate_url_template("https://example.com/{unknown}").is_err());
+        assert!(validate_url_template("https://example.com/{date:%Q}").is_err());
+        assert!(validate_url_template("https://example.com/{date").is_err());
+    }
+
+    #[test]
+    fn expands_caption_templates() {
+        let image_metadata = ImageMetadata {
+            title: String::from("Aurora"),
+            credit: String::from("ESA"),
+            capture_time: Some(Local.with_ymd_and_hms(2024, 5, 1, 22, 30, 0).unwrap()),
+            gps_position: Some((64.1, -21.9)),
+            ..ImageMetadata::default()
+        };
+
+        assert_eq!(
+            expand_caption_template(
+                "{title} ({credit}), {capture_time:%d.%m.%Y} at {latitude}, {longitude}",
+                &image_metadata
+            ),
+            Ok(String::from(
+                "Aurora (ESA), 01.05.2024 at 64.10000, -21.90000"
+            ))
+        );
+        assert_eq!(
+            expand_caption_template("{title}: {camera}{capture_time}", &ImageMetadata::default()),
+            Ok(String::from(": "))
+        );
+        assert!(expand_caption_template("{date}", &image_metadata).is_err());
+    }
+}
@@ -1,6 +1,6 @@
-use std::{ffi::OsStr, iter, os::windows::prelude::OsStrExt, path::PathBuf, env::current_dir};
+use std::{ffi::OsStr, iter, os::windows::prelude::OsStrExt, path::PathBuf, env::current_dir, ptr::null};
 
-use winapi::{um::winuser::{SystemParametersInfoW, SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SetSysColors, COLOR_BACKGROUND}, ctypes::c_void};
+use winapi::{um::{wincon::{AttachConsole, ATTACH_PARENT_PROCESS}, winuser::{FindWindowW, SetForegroundWindow, ShowWindow, SW_RESTORE, GetSystemMetrics, SM_CXSCREEN, SM_CYSCREEN, SystemParametersInfoW, SPI_SETDESKWALLPAPER, SPIF_SENDCHANGE, SPIF_UPDATEINIFILE, SetSysColors, COLOR_BACKGROUND}}, ctypes::c_void};
 use winreg::{enums::HKEY_CURRENT_USER, RegKey};
 
 use crate::{app_gui::{MyApp, Enum}, app_constants::AppConstants, common_utils::color32_to_reversed_u32};
@@ -47,11 +47,13 @@ pub fn set_pic_as_wallpaper(my_app: &MyApp) {
 
         if set_wallpaper_result == 1 {
             log::info!("Wallpaper set successfully");
+            my_app.wallpaper_validators.lock().unwrap().record_applied();
         } else {
             log::error!(
                 "Wallpaper setting failed - {}",
                 std::io::Error::last_os_error()
             );
+            my_app.metrics.lock().unwrap().record_wallpaper_apply_failure();
         }
     }
 }
@@ -91,4 +93,36 @@ pub fn set_background_color(color: [u8; 3]) {
 
 pub fn build_absolute_path(relative_path_str: &str) -> PathBuf {
     current_dir().unwrap().join(relative_path_str)
-}
\ No newline at end of file
+}
+// Release builds use the windows subsystem, so command line output needs the console of the launching shell
+pub fn attach_parent_console() {
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+// Resolution of the primary display in pixels
+pub fn get_screen_resolution() -> (i32, i32) {
+    unsafe { (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN)) }
+}
+
+// Restores the window of the running instance (the window title is the app name) and brings it
+// to the front
+pub fn show_app_window() {
+    let window_title = OsStr::new(AppConstants::APP_NAME)
+        .encode_wide()
+        .chain(iter::once(0))
+        .collect::<Vec<u16>>();
+
+    unsafe {
+        let window_handle = FindWindowW(null(), window_title.as_ptr());
+
+        if window_handle.is_null() {
+            log::warn!("App window not found, it cannot be shown");
+            return;
+        }
+
+        ShowWindow(window_handle, SW_RESTORE);
+        SetForegroundWindow(window_handle);
+    }
+}